@@ -8,6 +8,16 @@ pub enum Expr<'a> {
         name: &'a str,
         data: Box<Self>,
     },
+    /// A variable that can't be `set` or redefined. Literal values are inlined where they are used.
+    DefConst {
+        name: &'a str,
+        data: Box<Self>,
+    },
+    /// Generates a constructor, a `Name?` predicate, and `Name-field` accessors
+    DefStruct {
+        name: &'a str,
+        fields: Vec<&'a str>,
+    },
     Set {
         name: &'a str,
         data: Box<Self>,
@@ -21,6 +31,12 @@ pub enum Expr<'a> {
     Fn(Fn<'a>),
 
     Path(Vec<&'a str>),
+    /// An f-string. It calls the `std/string/fmt` builtin directly, so it still works if `std` is
+    /// shadowed.
+    FString {
+        template: String,
+        args: Vec<Self>,
+    },
 
     Cond {
         conditions: Vec<(Self, Self)>,
@@ -28,6 +44,19 @@ pub enum Expr<'a> {
     },
 
     Object(Vec<Field<'a>>),
+    /// Calls the method from the parent of the given vtable
+    Super {
+        vtable: Box<Self>,
+        method: &'a str,
+        args: Vec<Self>,
+    },
+
+    /// Runs the body once for each item of the iterator protocol
+    For {
+        var: &'a str,
+        iter: Box<Self>,
+        body: Vec<Self>,
+    },
 
     Quote(Box<Self>),
     Splat(Box<Self>),
@@ -44,6 +73,7 @@ pub enum Expr<'a> {
     Float(f64),
     String(String),
     Char(char),
+    Byte(u8),
     True,
     False,
 
@@ -56,6 +86,8 @@ pub enum Expr<'a> {
 pub enum Field<'a> {
     Full(&'a str, Expr<'a>),
     Shorthand(&'a str),
+    /// `...other` copies all the fields of another object
+    Splat(Expr<'a>),
 }
 
 #[derive(Debug, PartialEq)]
@@ -27,6 +27,7 @@ use std::{
     collections::VecDeque,
     fs::read_to_string,
     path::PathBuf,
+    mem::replace,
     rc::Rc,
 };
 use crate::{
@@ -51,27 +52,47 @@ pub enum Instruction {
     Nop,
     Exit,
 
-    ReturnModule,
+    /// The constants of the module, which can't be set through a path
+    ReturnModule(Vec<Ident>),
     Module(ModuleId),
+    /// Pushes the `std/string/fmt` builtin, so f-strings work even if `std` is shadowed
+    Fmt,
 
     /// Reads the previous result
     Define(Ident),
     /// Reads the previous result
     Set(Ident),
+    /// Reads the previous result. Sets the last field in the path on the object the rest of the
+    /// path points to.
+    SetPath(Vec<Ident>),
 
     FnOrClosure(FnId),
 
     Var(Ident),
     DotIdent(Ident),
 
-    Object(Vec<Ident>),
+    /// Reads a previous result for each field. `None` fields are objects to copy all the fields
+    /// from.
+    Object(Vec<Option<Ident>>),
 
     Path(Vec<Ident>),
 
+    /// Reads the previous result as a vtable, and pushes the named method from its parent chain
+    Super(Ident),
+
+    /// Reads the previous result. Pushes whether it is an instance of the named struct.
+    IsStruct(Ident),
+    /// Reads the previous result. Gets a field from an instance of the named struct.
+    StructField {
+        name: Ident,
+        field: Ident,
+    },
+
     Number(i64),
     Float(f64),
     String(String),
     Char(char),
+    Byte(u8),
     True,
     False,
 
@@ -88,6 +109,11 @@ pub enum Instruction {
     StartScope,
     EndScope,
 
+    /// Reads the previous result. Pushes an iterator for it.
+    IterStart,
+    /// Reads the previous result as an iterator. Pushes the next item, or jumps if it is done.
+    IterNext(InstructionId),
+
     /// Reads previous result
     JumpIfTrue(InstructionId),
     JumpIfFalse(InstructionId),
@@ -368,6 +394,11 @@ pub struct ConvertState {
     pub warnings: Vec<Error>,
     pub instructions: InstructionStore,
     pub modules: ModuleTree,
+    /// The constants defined at the top of each module, and the literal instruction to inline if
+    /// they have one
+    pub consts: IndexMap<(ModuleId, Ident), Option<Instruction>, FxBuildHasher>,
+    /// The scopes we are currently in, so constants can be defined and shadowed inside them
+    pub const_scopes: Vec<ConstScope>,
 }
 #[allow(dead_code)]
 impl ConvertState {
@@ -378,6 +409,8 @@ impl ConvertState {
             warnings: Vec::new(),
             instructions: InstructionStore::new(),
             modules: ModuleTree::new(),
+            consts: IndexMap::default(),
+            const_scopes: Vec::new(),
         }
     }
     #[inline]
@@ -390,14 +423,18 @@ impl ConvertState {
         self.warnings.push(err);
     }
 
+    /// Also closes the scope the call was started with
     #[inline]
     pub fn call_or_list(&mut self) {
         self.instructions.push(Instruction::Call);
+        self.pop_const_scope();
     }
 
+    /// Also closes the scope the call was started with
     #[inline]
     pub fn tail_call_or_list(&mut self) {
         self.instructions.push(Instruction::TailCall);
+        self.pop_const_scope();
     }
 
     #[inline]
@@ -405,13 +442,19 @@ impl ConvertState {
         self.instructions.push(Instruction::Return);
     }
 
-    #[inline]
-    pub fn push_module_return(&mut self) {
-        self.instructions.push(Instruction::ReturnModule);
+    pub fn push_module_return(&mut self, module: ModuleId) {
+        let consts = self.consts.keys()
+            .filter(|(m, _)|*m == module)
+            .map(|(_, i)|*i)
+            .collect();
+        self.instructions.push(Instruction::ReturnModule(consts));
     }
 
     pub fn define(&mut self, i: &str) {
         let ident = self.intern(i);
+        if let Some(scope) = self.const_scopes.last_mut() {
+            scope.vars.insert(ident);
+        }
 
         self.instructions.push(Instruction::Define(ident));
     }
@@ -422,12 +465,117 @@ impl ConvertState {
         self.instructions.push(Instruction::Set(ident));
     }
 
+    pub fn set_path(&mut self, path: Vec<&str>) {
+        let path = path.into_iter()
+            .map(|s|self.intern(s))
+            .collect();
+        self.instructions.push(Instruction::SetPath(path));
+    }
+
     pub fn ident(&mut self, i: &str) {
         let ident = self.intern(i);
 
         self.instructions.push(Instruction::Var(ident));
     }
 
+    /// Records a constant in the current scope, or the module if we are not in one. Only numbers,
+    /// strings, and bools are inlined.
+    pub fn define_const(&mut self, module: ModuleId, i: &str, data: &RefExpr)->Result<()> {
+        let ident = self.intern(i);
+        let literal = match data {
+            RefExpr::Number(n)=>Some(Instruction::Number(*n)),
+            RefExpr::Float(f)=>Some(Instruction::Float(*f)),
+            RefExpr::String(s)=>Some(Instruction::String(s.clone())),
+            RefExpr::Byte(b)=>Some(Instruction::Byte(*b)),
+            RefExpr::True=>Some(Instruction::True),
+            RefExpr::False=>Some(Instruction::False),
+            _=>None,
+        };
+
+        match self.const_scopes.last_mut() {
+            Some(scope)=>{
+                if scope.consts.contains_key(&ident) {
+                    bail!("Constant `{i}` is already defined");
+                }
+                if scope.vars.contains(&ident) {
+                    bail!("Var `{i}` already exists, so it can't be made a constant");
+                }
+                scope.consts.insert(ident, literal);
+            },
+            None=>{
+                if self.consts.contains_key(&(module, ident)) {
+                    bail!("Constant `{i}` is already defined");
+                }
+                self.consts.insert((module, ident), literal);
+            },
+        }
+
+        return Ok(());
+    }
+
+    /// Finds the constant the name resolves to, if any. `Some(None)` means it is a constant without
+    /// an inlineable value.
+    fn get_const(&self, module: ModuleId, ident: Ident)->Option<Option<&Instruction>> {
+        for scope in self.const_scopes.iter().rev() {
+            if scope.vars.contains(&ident) {
+                return scope.consts.get(&ident).map(Option::as_ref);
+            }
+        }
+
+        return self.consts.get(&(module, ident)).map(Option::as_ref);
+    }
+
+    /// Errors if the name resolves to a constant
+    pub fn check_not_const(&mut self, module: ModuleId, i: &str)->Result<()> {
+        let ident = self.intern(i);
+        if self.get_const(module, ident).is_some() {
+            bail!("Cannot assign to the constant `{i}`");
+        }
+
+        return Ok(());
+    }
+
+    /// Errors if the name is a constant in the current scope. Inner scopes can shadow it.
+    pub fn check_can_define(&mut self, module: ModuleId, i: &str)->Result<()> {
+        let ident = self.intern(i);
+        let is_const = match self.const_scopes.last() {
+            Some(scope)=>scope.consts.contains_key(&ident),
+            None=>self.consts.contains_key(&(module, ident)),
+        };
+        if is_const {
+            bail!("Cannot redefine the constant `{i}`");
+        }
+
+        return Ok(());
+    }
+
+    /// Pushes the literal value if the ident is an inlineable constant, otherwise reads the var
+    pub fn ident_or_const(&mut self, module: ModuleId, i: &str) {
+        let ident = self.intern(i);
+
+        match self.get_const(module, ident) {
+            Some(Some(ins))=>{
+                let ins = ins.clone();
+                self.instructions.push(ins);
+            },
+            _=>{
+                self.instructions.push(Instruction::Var(ident));
+            },
+        }
+    }
+
+    /// Only tracks the scope for constants. `start_scope` and friends call this.
+    pub fn push_const_scope(&mut self, vars: IndexSet<Ident, FxBuildHasher>) {
+        self.const_scopes.push(ConstScope {
+            vars,
+            consts: IndexMap::default(),
+        });
+    }
+
+    pub fn pop_const_scope(&mut self) {
+        self.const_scopes.pop();
+    }
+
     pub fn dot_ident(&mut self, i: &str) {
         let ident = self.intern(i);
 
@@ -492,11 +640,13 @@ impl ConvertState {
     #[inline]
     pub fn start_scope(&mut self) {
         self.instructions.push(Instruction::StartScope);
+        self.push_const_scope(IndexSet::default());
     }
 
     #[inline]
     pub fn end_scope(&mut self) {
         self.instructions.push(Instruction::EndScope);
+        self.pop_const_scope();
     }
 
     #[inline]
@@ -515,13 +665,19 @@ impl ConvertState {
     }
 
     #[inline]
-    pub fn object(&mut self, fields: Vec<Ident>) {
+    pub fn byte(&mut self, b: u8) {
+        self.instructions.push(Instruction::Byte(b));
+    }
+
+    #[inline]
+    pub fn object(&mut self, fields: Vec<Option<Ident>>) {
         self.instructions.push(Instruction::Object(fields));
     }
 
     #[inline]
     pub fn start_return_scope(&mut self) {
         self.instructions.push(Instruction::StartReturnScope);
+        self.push_const_scope(IndexSet::default());
     }
 
     pub fn push_path(&mut self, path: Vec<&str>) {
@@ -558,6 +714,13 @@ impl ConvertState {
     }
 }
 
+/// The names a scope defines, so we know when they shadow a constant from an outer scope
+#[derive(Debug, Clone)]
+pub struct ConstScope {
+    pub vars: IndexSet<Ident, FxBuildHasher>,
+    pub consts: IndexMap<Ident, Option<Instruction>, FxBuildHasher>,
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct ModuleNode {
@@ -602,7 +765,9 @@ struct TodoModule {
 }
 
 struct Todos<'a, 'b> {
-    pub fns: VecDeque<(FnId, RefFn<'a>)>,
+    /// Functions are converted after the code around them, so each one keeps the constant scopes it
+    /// was defined in
+    pub fns: VecDeque<(FnId, RefFn<'a>, Vec<ConstScope>)>,
     pub modules: &'b mut VecDeque<TodoModule>,
 
     /// Helper to temporarily store the children of the current module
@@ -623,8 +788,8 @@ impl<'a, 'b> Todos<'a, 'b> {
         }
     }
 
-    fn queue_fn(&mut self, id: FnId, f: RefFn<'a>) {
-        self.fns.push_back((id, f));
+    fn queue_fn(&mut self, id: FnId, f: RefFn<'a>, const_scopes: Vec<ConstScope>) {
+        self.fns.push_back((id, f, const_scopes));
     }
 
     fn queue_module(&mut self, id: ModuleId, name: &str) {
@@ -652,8 +817,8 @@ pub fn convert<'a>(exprs: Vec<RefExpr<'a>>)->Result<ConvertState> {
 
     state.push_exit();
     
-    while let Some((id, f)) = todos.fns.pop_back() {
-        convert_fn(&mut state, &mut todos, f, id)?;
+    while let Some((id, f, const_scopes)) = todos.fns.pop_back() {
+        convert_fn(&mut state, &mut todos, f, id, const_scopes)?;
     }
 
     let root_children = todos.new_modules;
@@ -673,6 +838,9 @@ pub fn convert<'a>(exprs: Vec<RefExpr<'a>>)->Result<ConvertState> {
 }
 
 pub fn repl_convert<'a>(state: &mut ConvertState, exprs: Vec<RefExpr<'a>>)->Result<InstructionId> {
+    // a previous line could have errored inside a scope
+    state.const_scopes.clear();
+
     let start_id = state.next_ins_id();
     let mut module_todos = VecDeque::new();
     let mut todos = Todos::new(&mut module_todos);
@@ -680,8 +848,8 @@ pub fn repl_convert<'a>(state: &mut ConvertState, exprs: Vec<RefExpr<'a>>)->Resu
 
     state.push_exit();
     
-    while let Some((id, f)) = todos.fns.pop_front() {
-        convert_fn(state, &mut todos, f, id)?;
+    while let Some((id, f, const_scopes)) = todos.fns.pop_front() {
+        convert_fn(state, &mut todos, f, id, const_scopes)?;
     }
 
     while let Some(todo) = module_todos.pop_back() {
@@ -727,10 +895,10 @@ fn convert_module<'a>(state: &mut ConvertState, module_todos: &'a mut VecDeque<T
         bail!(ModuleError);
     }
 
-    state.push_module_return();
+    state.push_module_return(module_todo.id);
 
-    while let Some((id, f)) = todos.fns.pop_back() {
-        if let Err(e) = convert_fn(state, &mut todos, f, id) {
+    while let Some((id, f, const_scopes)) = todos.fns.pop_back() {
+        if let Err(e) = convert_fn(state, &mut todos, f, id, const_scopes) {
             error_trace(e, &source, path.display());
             bail!(ModuleError);
         }
@@ -766,7 +934,8 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
         RefExpr::Float(f)=>state.float(f),
         RefExpr::String(s)=>state.string(s),
         RefExpr::Char(c)=>state.char(c),
-        RefExpr::Ident(i)=>state.ident(i),
+        RefExpr::Byte(b)=>state.byte(b),
+        RefExpr::Ident(i)=>state.ident_or_const(todos.current_module, i),
         RefExpr::DotIdent(i)=>state.dot_ident(i),
         RefExpr::Comment(_)=>{},
         RefExpr::Module(name)=>{
@@ -775,30 +944,53 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
             todos.queue_module(id, name);
         },
         RefExpr::Def{name, data}=>{
+            state.check_can_define(todos.current_module, name)?;
+
+            convert_single_expr(state, todos, *data, is_tail)?;
+
+            state.define(name);
+        },
+        RefExpr::DefConst{name, data}=>{
+            state.define_const(todos.current_module, name, &data)?;
+
             convert_single_expr(state, todos, *data, is_tail)?;
 
             state.define(name);
         },
+        RefExpr::DefStruct{name, fields}=>convert_defstruct(state, todos, name, fields)?,
         RefExpr::Set{name, data}=>{
+            state.check_not_const(todos.current_module, name)?;
+
             convert_single_expr(state, todos, *data, is_tail)?;
 
             state.set_var(name);
         },
-        // RefExpr::SetPath{path, data}=>{
-        RefExpr::SetPath{..}=>{
-            todo!();
+        RefExpr::SetPath{path, data}=>{
+            convert_single_expr(state, todos, *data, is_tail)?;
+
+            state.set_path(path);
         },
         RefExpr::Object(fields)=>{
             state.start_scope();
             let mut new_fields = Vec::with_capacity(fields.len());
             for field in fields {
+                // only struct constructors can make a `$type`, so objects can't pretend to be structs
+                if let RefField::Shorthand("$type")|RefField::Full("$type", _) = field {
+                    bail!("Cannot set the `$type` of an object");
+                }
                 match field {
                     RefField::Shorthand(i)=>{
-                        new_fields.push(state.intern(i));
+                        new_fields.push(Some(state.intern(i)));
                         state.ident(i);
                     },
                     RefField::Full(i, expr)=>{
-                        new_fields.push(state.intern(i));
+                        new_fields.push(Some(state.intern(i)));
+                        state.start_scope();
+                        convert_single_expr(state, todos, expr, NOT_TAIL)?;
+                        state.end_scope();
+                    },
+                    RefField::Splat(expr)=>{
+                        new_fields.push(None);
                         state.start_scope();
                         convert_single_expr(state, todos, expr, NOT_TAIL)?;
                         state.end_scope();
@@ -813,9 +1005,42 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
         RefExpr::Path(path)=>{
             state.push_path(path);
         },
+        RefExpr::FString{template, args}=>{
+            state.start_scope();
+
+            state.instructions.push(Instruction::Fmt);
+            state.string(template);
+            for arg in args {
+                convert_single_expr(state, todos, arg, NOT_TAIL)?;
+            }
+
+            if is_tail {
+                state.tail_call_or_list();
+            } else {
+                state.call_or_list();
+            }
+        },
+        RefExpr::Super{vtable, method, args}=>{
+            state.start_scope();
+
+            convert_single_expr(state, todos, *vtable, NOT_TAIL)?;
+            let method = state.intern(method);
+            state.instructions.push(Instruction::Super(method));
+
+            for arg in args {
+                convert_single_expr(state, todos, arg, NOT_TAIL)?;
+            }
+
+            if is_tail {
+                state.tail_call_or_list();
+            } else {
+                state.call_or_list();
+            }
+        },
+        RefExpr::For{var, iter, body}=>convert_for(state, todos, var, *iter, body)?,
         RefExpr::Fn(f)=>{
             let id = state.reserve_func();
-            todos.queue_fn(id, f);
+            todos.queue_fn(id, f, state.const_scopes.clone());
 
             state.function(id);
         },
@@ -873,6 +1098,9 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
             } else {
                 assert!(jump_ends.is_empty());
             }
+
+            // there is no `EndScope` for a cond, but constants defined in it stop here
+            state.pop_const_scope();
         },
         RefExpr::Splat(expr)=>{
             convert_single_expr(state, todos, *expr, NOT_TAIL)?;
@@ -904,9 +1132,126 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
     })
 }
 
-fn convert_fn<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, 'b>, func: RefFn<'a>, id: FnId)->Result<()> {
+/// The iterator is kept in a hidden variable in the loop scope, and each pass gets its own scope
+/// for the loop variable. A `for` always results in `None`.
+fn convert_for<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, 'b>, var: &'a str, iter: RefExpr<'a>, body: Vec<RefExpr<'a>>)->Result<()> {
+    let iter_var = state.intern("$for");
+
+    state.start_return_scope();
+    convert_single_expr(state, todos, iter, NOT_TAIL)?;
+    state.instructions.push(Instruction::IterStart);
+    state.instructions.push(Instruction::Define(iter_var));
+
+    let loop_start = state.next_ins_id();
+    state.start_return_scope();
+    state.instructions.push(Instruction::Var(iter_var));
+    let iter_next = state.instructions.push(Instruction::Exit);
+    state.define(var);
+
+    if !body.is_empty() {
+        convert_exprs(state, todos, body, NOT_TAIL)?;
+    }
+
+    state.end_scope();
+    state.instructions.push(Instruction::Jump(loop_start));
+
+    // the body scope is still open when the iterator is done
+    let loop_end = state.next_ins_id();
+    state.instructions.set(iter_next, Instruction::IterNext(loop_end));
+    // its constant scope was already closed above, so only the runtime scope is ended here
+    state.instructions.push(Instruction::EndScope);
+    state.push_none();
+    state.end_scope();
+
+    return Ok(());
+}
+
+/// Structs are objects with a `$type` field. The generated functions are put inline, so we jump
+/// over their bodies before defining them.
+fn convert_defstruct<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, 'b>, name: &'a str, fields: Vec<&'a str>)->Result<()> {
+    // the type includes the module path, so structs with the same name in different modules are
+    // different types
+    let mut type_path = todos.module_path.iter()
+        .map(|s|s.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    type_path.push(name.to_string());
+    let type_name = state.intern(&type_path.join("/"));
+    let type_field = state.intern("$type");
+    let obj_param = state.intern("obj");
+    let fields = fields.into_iter()
+        .map(|f|state.intern(f))
+        .collect::<Vec<_>>();
+
+    let skip_jump = state.instructions.push(Instruction::Exit);
+    let mut fns = Vec::new();
+
+    // constructor
+    let body_ptr = state.next_ins_id();
+    state.start_scope();
+    state.instructions.push(Instruction::DotIdent(type_name));
+    for field in fields.iter().copied() {
+        state.instructions.push(Instruction::Var(field));
+    }
+    let mut object_fields = vec![Some(type_field)];
+    object_fields.extend(fields.iter().copied().map(Some));
+    state.object(object_fields);
+    state.end_scope();
+    state.push_return();
+
+    fns.push((name.to_string(), Vector {items: fields.clone(), remainder: None}, body_ptr));
+
+    // predicate
+    let body_ptr = state.next_ins_id();
+    state.instructions.push(Instruction::Var(obj_param));
+    state.instructions.push(Instruction::IsStruct(type_name));
+    state.push_return();
+
+    fns.push((format!("{name}?"), Vector {items: vec![obj_param], remainder: None}, body_ptr));
+
+    // accessors
+    for field in fields.iter().copied() {
+        let body_ptr = state.next_ins_id();
+        state.instructions.push(Instruction::Var(obj_param));
+        state.instructions.push(Instruction::StructField {
+            name: type_name,
+            field,
+        });
+        state.push_return();
+
+        let fn_name = format!("{name}-{}", state.interner.get(field));
+        fns.push((fn_name, Vector {items: vec![obj_param], remainder: None}, body_ptr));
+    }
+
+    let after_id = state.next_ins_id();
+    state.instructions.set(skip_jump, Instruction::Jump(after_id));
+
+    for (fn_name, params, body_ptr) in fns {
+        state.check_can_define(todos.current_module, &fn_name)?;
+
+        let id = state.reserve_func();
+        let fn_ident = state.intern(&fn_name);
+        state.fns.insert_reserved(id, Rc::new(Fn {
+            id,
+            name: Some(fn_ident),
+            captures: Vec::new(),
+            sig: FnSignature::Single {params, body_ptr},
+        })).unwrap();
+
+        state.function(id);
+        state.define(&fn_name);
+    }
+
+    return Ok(());
+}
+
+fn convert_fn<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, 'b>, func: RefFn<'a>, id: FnId, const_scopes: Vec<ConstScope>)->Result<()> {
     let name = func.name.map(|n|state.intern(n));
-    let sig = convert_signature(state, todos, func.signature)?;
+
+    // the body sees the constants and shadowing of the scopes the function was defined in
+    let outer_scopes = replace(&mut state.const_scopes, const_scopes);
+    let sig = convert_signature(state, todos, func.signature);
+    state.const_scopes = outer_scopes;
+    let sig = sig?;
     let captures = func.captures
         .map(|c|c.items
             .into_iter()
@@ -930,7 +1275,9 @@ fn convert_signature<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, 'b>
             let params = convert_vector(state, params);
 
             let body_ptr = state.next_ins_id();
+            push_params_scope(state, &params);
             convert_exprs(state, todos, body, IS_TAIL)?;
+            state.pop_const_scope();
             state.push_return();
 
             return Ok(FnSignature::Single{params, body_ptr});
@@ -945,7 +1292,9 @@ fn convert_signature<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, 'b>
                 let params = convert_vector(state, params);
 
                 let body_ptr = state.next_ins_id();
+                push_params_scope(state, &params);
                 convert_exprs(state, todos, body, IS_TAIL)?;
+                state.pop_const_scope();
                 state.push_return();
 
                 if params.remainder.is_some() {
@@ -970,6 +1319,15 @@ fn convert_signature<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, 'b>
     }
 }
 
+/// Parameters shadow constants from the module for the whole body
+fn push_params_scope(state: &mut ConvertState, params: &Vector) {
+    let vars = params.items.iter()
+        .chain(params.remainder.iter())
+        .copied()
+        .collect();
+    state.push_const_scope(vars);
+}
+
 fn convert_vector<'a>(state: &mut ConvertState, vector: RefVector<'a>)->Vector {
     let mut items = Vec::new();
     let mut remainder = None;
@@ -984,3 +1342,48 @@ fn convert_vector<'a>(state: &mut ConvertState, vector: RefVector<'a>)->Vector {
 
     return Vector {items, remainder};
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_source(source: &str)->Result<ConvertState> {
+        let exprs = crate::parser::new_parser(source).parse_all()?;
+        return convert(exprs);
+    }
+
+    /// Whether the name is read as a var anywhere instead of being inlined as a constant
+    fn reads_var(state: &mut ConvertState, name: &str)->bool {
+        let ident = state.intern(name);
+        return state.instructions.iter()
+            .any(|ins|matches!(ins, Instruction::Var(i) if *i == ident));
+    }
+
+    #[test]
+    fn params_shadow_consts_after_for() {
+        let mut state = convert_source("(defconst N 3) (defn f [N] (for [x (list 1)] x) N)").unwrap();
+        assert!(reads_var(&mut state, "N"));
+    }
+
+    #[test]
+    fn local_consts_stay_scoped_after_for() {
+        let res = convert_source("(defn f [] (defconst X 1) (for [x (list 1)] x) (defconst X 2))");
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn closures_see_enclosing_params() {
+        let mut state = convert_source("(defconst N 3) (defn f [N] (fn [] N))").unwrap();
+        assert!(reads_var(&mut state, "N"));
+    }
+
+    #[test]
+    fn closures_see_enclosing_consts() {
+        let mut state = convert_source("(defn f [] (defconst X 7) (fn [] X))").unwrap();
+        assert!(!reads_var(&mut state, "X"));
+
+        let res = convert_source("(defn f [] (defconst X 7) (fn [] (set X 8)))");
+        assert!(res.is_err());
+    }
+}
@@ -2,9 +2,18 @@ use anyhow::{
     Result,
     bail,
 };
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{
+    ToPrimitive,
+    FromPrimitive,
+    Signed,
+};
+use std::cmp::Ordering;
 use super::{
     Interpreter,
-    Interner,
+    ConvertState,
+    Ident,
     Data,
     DataRef,
     NativeFn,
@@ -13,36 +22,16 @@ use super::{
 
 
 macro_rules! define_arithmetic_func {
-    ($name: ident, $sym: tt)=>{
-        pub fn $name(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+    ($name: ident, $op: expr)=>{
+        pub fn $name(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
             if args.is_empty() {return Ok(i.alloc(Data::Number(0)))}
 
             let mut iter = args.into_iter();
 
-            fn do_the_thing(d1: &mut Data, d2: &Data)->Result<()> {
-                match d1 {
-                    Data::Number(n1)=>{
-                        let Data::Number(n2) = d2 else {
-                            bail!("Type error: Expected number");
-                        };
-                        *n1 $sym *n2;
-                    },
-                    Data::Float(f1)=>{
-                        let Data::Float(f2) = d2 else {
-                            bail!("Type error: Expected float");
-                        };
-
-                        *f1 $sym *f2;
-                    },
-                    _=>bail!(concat!("Type error: ", stringify!($name), " can only accept number or float")),
-                }
-                return Ok(());
-            }
-
             let mut first = i.clone_data(&iter.next().unwrap());
 
             for arg in iter {
-                do_the_thing(&mut first.get_data_mut(), &arg.get_data())?;
+                number_op(&mut first.get_data_mut(), &arg.get_data(), $op, stringify!($name))?;
             }
 
             return Ok(first);
@@ -51,36 +40,16 @@ macro_rules! define_arithmetic_func {
 }
 
 macro_rules! define_arithmetic_assign_func {
-    ($name: ident, $sym: tt)=>{
-        pub fn $name(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+    ($name: ident, $op: expr)=>{
+        pub fn $name(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
             if args.is_empty() {return Ok(i.alloc(Data::Number(0)))}
 
             let mut iter = args.into_iter();
 
-            fn do_the_thing(d1: &mut Data, d2: &Data)->Result<()> {
-                match d1 {
-                    Data::Number(n1)=>{
-                        let Data::Number(n2) = d2 else {
-                            bail!("Type error: Expected number");
-                        };
-                        *n1 $sym *n2;
-                    },
-                    Data::Float(f1)=>{
-                        let Data::Float(f2) = d2 else {
-                            bail!("Type error: Expected float");
-                        };
-
-                        *f1 $sym *f2;
-                    },
-                    _=>bail!(concat!("Type error: ", stringify!($name), " can only accept number or float")),
-                }
-                return Ok(());
-            }
-
             let mut first = iter.next().unwrap();
 
             for arg in iter {
-                do_the_thing(&mut first.get_data_mut(), &arg.get_data())?;
+                number_op(&mut first.try_get_data_mut()?, &arg.get_data(), $op, stringify!($name))?;
             }
 
             return Ok(first);
@@ -108,18 +77,145 @@ pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
     builtin!(less, <, Any),
     builtin!(greater_equal, >=, Any),
     builtin!(less_equal, <=, Any),
+
+    // the names have dashes, which `builtin!` can't take
+    ("bit-and", bit_and, ArgCount::Any),
+    ("bit-or", bit_or, ArgCount::Any),
+    ("bit-xor", bit_xor, ArgCount::Any),
+    ("bit-not", bit_not, ArgCount::Exact(1)),
+    ("shift-left", shift_left, ArgCount::Exact(2)),
+    ("shift-right", shift_right, ArgCount::Exact(2)),
+    ("shift-right-logical", shift_right_logical, ArgCount::Exact(2)),
+    ("leading-zeros", leading_zeros, ArgCount::Exact(1)),
+    builtin!(popcount, 1),
+    builtin!(floor_div, div, 2),
+    builtin!(floor_rem, rem, 2),
 ];
 
 
-fn do_the_thing_add(d1: &mut Data, d2: &Data)->Result<()> {
-    match d1 {
-        Data::Number(n1)=>{
-            let Data::Number(n2) = d2 else {
-                bail!("Type error: Expected number");
-            };
+#[derive(Copy, Clone)]
+enum NumberOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+impl NumberOp {
+    /// `None` if it overflowed
+    fn int(self, l: i64, r: i64)->Option<i64> {
+        match self {
+            Self::Add=>l.checked_add(r),
+            Self::Sub=>l.checked_sub(r),
+            Self::Mul=>l.checked_mul(r),
+            Self::Div=>l.checked_div(r),
+            Self::Rem=>l.checked_rem(r),
+        }
+    }
+
+    fn big(self, l: &BigInt, r: &BigInt)->BigInt {
+        match self {
+            Self::Add=>l + r,
+            Self::Sub=>l - r,
+            Self::Mul=>l * r,
+            Self::Div=>l / r,
+            Self::Rem=>l % r,
+        }
+    }
+
+    fn float(self, l: f64, r: f64)->f64 {
+        match self {
+            Self::Add=>l + r,
+            Self::Sub=>l - r,
+            Self::Mul=>l * r,
+            Self::Div=>l / r,
+            Self::Rem=>l % r,
+        }
+    }
+}
+
+/// Any numeric value as a Float
+pub fn as_f64(data: &Data)->Option<f64> {
+    match data {
+        Data::Number(n)=>Some(*n as f64),
+        Data::BigInt(n)=>n.to_f64(),
+        Data::Float(f)=>Some(*f),
+        _=>None,
+    }
+}
 
-            *n1 += n2;
+/// Numbers and BigInts as a BigInt
+pub fn as_bigint(data: &Data)->Option<BigInt> {
+    match data {
+        Data::Number(n)=>Some(BigInt::from(*n)),
+        Data::BigInt(n)=>Some(n.clone()),
+        _=>None,
+    }
+}
+
+/// Orders any two numeric values. `None` if either is not numeric, or a Float is NaN.
+fn number_order(l: &Data, r: &Data)->Option<Ordering> {
+    match (l, r) {
+        (Data::Number(l), Data::Number(r))=>Some(l.cmp(r)),
+        (Data::Byte(l), Data::Byte(r))=>Some(l.cmp(r)),
+        (Data::Float(l), Data::Float(r))=>l.partial_cmp(r),
+        (Data::Float(f), n)=>float_int_order(*f, &as_bigint(n)?),
+        (n, Data::Float(f))=>float_int_order(*f, &as_bigint(n)?).map(Ordering::reverse),
+        _=>Some(as_bigint(l)?.cmp(&as_bigint(r)?)),
+    }
+}
+
+/// Compares exactly instead of rounding the integer to a Float, so numbers that are `=` are also
+/// the same key in sets and maps
+fn float_int_order(f: f64, n: &BigInt)->Option<Ordering> {
+    if f.is_nan() {
+        return None;
+    }
+    if f.is_infinite() {
+        return Some(if f > 0.0 {Ordering::Greater} else {Ordering::Less});
+    }
+
+    let floor = BigInt::from_f64(f.floor())?;
+    match floor.cmp(n) {
+        Ordering::Equal if f.fract() != 0.0=>Some(Ordering::Greater),
+        order=>Some(order),
+    }
+}
+
+/// Does the op in place. Numbers that overflow become a BigInt, and a Float on either side makes
+/// the result a Float. Integer division truncates like Rust's.
+fn number_op(d1: &mut Data, d2: &Data, op: NumberOp, name: &str)->Result<()> {
+    let is_int = matches!(d1, Data::Number(_)|Data::BigInt(_));
+    if is_int && matches!(op, NumberOp::Div|NumberOp::Rem) && matches!(d2, Data::Number(0)) {
+        bail!("Division by zero");
+    }
+
+    let res = match (&*d1, d2) {
+        (Data::Number(l), Data::Number(r))=>match op.int(*l, *r) {
+            Some(n)=>Data::Number(n),
+            None=>Data::from_bigint(op.big(&BigInt::from(*l), &BigInt::from(*r))),
         },
+        (Data::Float(_), _)|(_, Data::Float(_))=>{
+            let (Some(l), Some(r)) = (as_f64(d1), as_f64(d2)) else {
+                bail!("Type error: `{name}` can only accept numbers and floats");
+            };
+            Data::Float(op.float(l, r))
+        },
+        _=>{
+            let (Some(l), Some(r)) = (as_bigint(d1), as_bigint(d2)) else {
+                bail!("Type error: `{name}` can only accept numbers and floats");
+            };
+            Data::from_bigint(op.big(&l, &r))
+        },
+    };
+    *d1 = res;
+
+    return Ok(());
+}
+
+fn do_the_thing_add(d1: &mut Data, d2: &Data)->Result<()> {
+    match d1 {
+        Data::Number(_)|Data::BigInt(_)|Data::Float(_)=>number_op(d1, d2, NumberOp::Add, "add")?,
         Data::String(out)=>{
             match d2 {
                 Data::String(s)=>{
@@ -146,13 +242,6 @@ fn do_the_thing_add(d1: &mut Data, d2: &Data)->Result<()> {
                 _=>bail!("Type error: Expected string or char"),
             }
         },
-        Data::Float(f1)=>{
-            let Data::Float(f2) = d2 else {
-                bail!("Type error: Expected float");
-            };
-
-            *f1 += f2;
-        },
         Data::Object(fields1)=>{
             let Data::Object(fields2) = d2 else {
                 bail!("Type error: Expected object");
@@ -165,9 +254,96 @@ fn do_the_thing_add(d1: &mut Data, d2: &Data)->Result<()> {
     return Ok(());
 }
 
-pub fn add(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+/// Finds the method on either operand. It is always called as `(method left right)`.
+fn operand_method(l: &DataRef, r: &DataRef, name: Ident, i: &Interpreter)->Result<Option<DataRef>> {
+    if let Some(method) = i.get_method(l, name)? {
+        return Ok(Some(method));
+    }
+
+    return i.get_method(r, name);
+}
+
+/// Numbers of different kinds are compared by value, the same as `<` and friends, so `(= 1 1.0)`
+pub fn data_eq(l: &DataRef, r: &DataRef, i: &mut Interpreter, state: &mut ConvertState)->Result<bool> {
+    match operand_method(l, r, i.protocol.eq, i)? {
+        Some(method)=>{
+            let res = i.call_value(method, vec![l.clone(), r.clone()], state)?;
+            return match &*res.get_data() {
+                Data::Bool(b)=>Ok(*b),
+                _=>bail!("Type error: `$eq` must return a Bool"),
+            };
+        },
+        None=>{
+            let (l, r) = (l.get_data(), r.get_data());
+            if let Some(order) = number_order(&l, &r) {
+                return Ok(order == Ordering::Equal);
+            }
+
+            return Ok(*l == *r);
+        },
+    }
+}
+
+/// `None` if neither operand has a `$lt` method
+fn data_lt(l: &DataRef, r: &DataRef, i: &mut Interpreter, state: &mut ConvertState)->Result<Option<bool>> {
+    match operand_method(l, r, i.protocol.lt, i)? {
+        Some(method)=>{
+            let res = i.call_value(method, vec![l.clone(), r.clone()], state)?;
+            return match &*res.get_data() {
+                Data::Bool(b)=>Ok(Some(*b)),
+                _=>bail!("Type error: `$lt` must return a Bool"),
+            };
+        },
+        None=>Ok(None),
+    }
+}
+
+/// Less-than for things that need an ordering, like sorting. Errors if the values can't be
+/// compared.
+pub fn compare_less(l: &DataRef, r: &DataRef, i: &mut Interpreter, state: &mut ConvertState)->Result<bool> {
+    if let Some(lt) = data_lt(l, r, i, state)? {
+        return Ok(lt);
+    }
+
+    let l = l.get_data();
+    let r = r.get_data();
+    if let Some(order) = number_order(&l, &r) {
+        return Ok(order == Ordering::Less);
+    }
+
+    match (&*l, &*r) {
+        (Data::String(l), Data::String(r))=>Ok(l < r),
+        (Data::Char(l), Data::Char(r))=>Ok(l < r),
+        // NaN is not less than anything
+        (l, r) if as_f64(l).is_some() && as_f64(r).is_some()=>Ok(false),
+        _=>bail!("Type error: Cannot compare these values"),
+    }
+}
+
+pub fn add(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     if args.is_empty() {return Ok(i.alloc(Data::Number(0)))}
 
+    // objects with `$add` are added pairwise from the left
+    let mut has_method = false;
+    for arg in args.iter() {
+        if i.get_method(arg, i.protocol.add)?.is_some() {
+            has_method = true;
+            break;
+        }
+    }
+    if has_method {
+        let mut iter = args.into_iter();
+        let mut acc = iter.next().unwrap();
+        for arg in iter {
+            acc = match operand_method(&acc, &arg, i.protocol.add, i)? {
+                Some(method)=>i.call_value(method, vec![acc, arg], state)?,
+                None=>add(vec![acc, arg], i, state)?,
+            };
+        }
+
+        return Ok(acc);
+    }
+
     let mut iter = args.into_iter();
 
 
@@ -191,14 +367,14 @@ pub fn add(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<Da
     return Ok(first);
 }
 
-pub fn add_assign(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn add_assign(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     if args.is_empty() {return Ok(i.alloc(Data::Number(0)))}
 
     let mut iter = args.into_iter();
 
 
     let mut first = iter.next().unwrap();
-    let mut first_mut = first.get_data_mut();
+    let mut first_mut = first.try_get_data_mut()?;
 
     match &mut *first_mut {
         Data::List(items)=>{
@@ -217,7 +393,7 @@ pub fn add_assign(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Re
     return Ok(first);
 }
 
-pub fn equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn equal(mut args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     if args.len() == 0 {
         return Ok(i.alloc(Data::Bool(true)));
     }
@@ -225,7 +401,7 @@ pub fn equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Res
     let first = args.pop().unwrap();
 
     for arg in args {
-        if &*arg.get_data() != &*first.get_data() {
+        if !data_eq(&arg, &first, i, state)? {
             return Ok(i.alloc(Data::Bool(false)));
         }
     }
@@ -233,7 +409,7 @@ pub fn equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Res
     return Ok(i.alloc(Data::Bool(true)));
 }
 
-pub fn not_equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn not_equal(mut args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     if args.len() == 0 {
         return Ok(i.alloc(Data::Bool(true)));
     }
@@ -241,7 +417,7 @@ pub fn not_equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)-
     let first = args.pop().unwrap();
 
     for arg in args {
-        if &*arg.get_data() == &*first.get_data() {
+        if data_eq(&arg, &first, i, state)? {
             return Ok(i.alloc(Data::Bool(false)));
         }
     }
@@ -249,7 +425,7 @@ pub fn not_equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)-
     return Ok(i.alloc(Data::Bool(true)));
 }
 
-pub fn less_equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn less_equal(mut args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     if args.len() == 0 {
         return Ok(i.alloc(Data::Bool(true)));
     }
@@ -257,17 +433,21 @@ pub fn less_equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)
     let first = args.pop().unwrap();
 
     for arg in args {
-        match (&*arg.get_data(), &*first.get_data()) {
-            (Data::Number(l), Data::Number(r))=>if l > r {return Ok(i.alloc(Data::Bool(false)))},
-            (Data::Float(l), Data::Float(r))=>if l > r {return Ok(i.alloc(Data::Bool(false)))},
-            _=>return Ok(i.alloc(Data::Bool(false))),
+        if let Some(lt) = data_lt(&first, &arg, i, state)? {
+            if lt {return Ok(i.alloc(Data::Bool(false)))}
+            continue;
+        }
+
+        match number_order(&arg.get_data(), &first.get_data()) {
+            Some(Ordering::Greater)|None=>return Ok(i.alloc(Data::Bool(false))),
+            _=>{},
         }
     }
 
     return Ok(i.alloc(Data::Bool(true)));
 }
 
-pub fn greater_equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn greater_equal(mut args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     if args.len() == 0 {
         return Ok(i.alloc(Data::Bool(true)));
     }
@@ -275,17 +455,21 @@ pub fn greater_equal(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Intern
     let first = args.pop().unwrap();
 
     for arg in args {
-        match (&*arg.get_data(), &*first.get_data()) {
-            (Data::Number(l), Data::Number(r))=>if l < r {return Ok(i.alloc(Data::Bool(false)))},
-            (Data::Float(l), Data::Float(r))=>if l < r {return Ok(i.alloc(Data::Bool(false)))},
-            _=>return Ok(i.alloc(Data::Bool(false))),
+        if let Some(lt) = data_lt(&arg, &first, i, state)? {
+            if lt {return Ok(i.alloc(Data::Bool(false)))}
+            continue;
+        }
+
+        match number_order(&arg.get_data(), &first.get_data()) {
+            Some(Ordering::Less)|None=>return Ok(i.alloc(Data::Bool(false))),
+            _=>{},
         }
     }
 
     return Ok(i.alloc(Data::Bool(true)));
 }
 
-pub fn less(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn less(mut args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     if args.len() == 0 {
         return Ok(i.alloc(Data::Bool(true)));
     }
@@ -293,17 +477,21 @@ pub fn less(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Resu
     let first = args.pop().unwrap();
 
     for arg in args {
-        match (&*arg.get_data(), &*first.get_data()) {
-            (Data::Number(l), Data::Number(r))=>if l >= r {return Ok(i.alloc(Data::Bool(false)))},
-            (Data::Float(l), Data::Float(r))=>if l >= r {return Ok(i.alloc(Data::Bool(false)))},
-            _=>return Ok(i.alloc(Data::Bool(false))),
+        if let Some(lt) = data_lt(&arg, &first, i, state)? {
+            if !lt {return Ok(i.alloc(Data::Bool(false)))}
+            continue;
+        }
+
+        match number_order(&arg.get_data(), &first.get_data()) {
+            Some(Ordering::Greater|Ordering::Equal)|None=>return Ok(i.alloc(Data::Bool(false))),
+            _=>{},
         }
     }
 
     return Ok(i.alloc(Data::Bool(true)));
 }
 
-pub fn greater(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn greater(mut args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     if args.len() == 0 {
         return Ok(i.alloc(Data::Bool(true)));
     }
@@ -311,22 +499,297 @@ pub fn greater(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->R
     let first = args.pop().unwrap();
 
     for arg in args {
-        match (&*arg.get_data(), &*first.get_data()) {
-            (Data::Number(l), Data::Number(r))=>if l <= r {return Ok(i.alloc(Data::Bool(false)))},
-            (Data::Float(l), Data::Float(r))=>if l <= r {return Ok(i.alloc(Data::Bool(false)))},
-            _=>return Ok(i.alloc(Data::Bool(false))),
+        if let Some(lt) = data_lt(&first, &arg, i, state)? {
+            if !lt {return Ok(i.alloc(Data::Bool(false)))}
+            continue;
+        }
+
+        match number_order(&arg.get_data(), &first.get_data()) {
+            Some(Ordering::Less|Ordering::Equal)|None=>return Ok(i.alloc(Data::Bool(false))),
+            _=>{},
         }
     }
 
     return Ok(i.alloc(Data::Bool(true)));
 }
 
-define_arithmetic_func!(sub, -=);
-define_arithmetic_func!(mul, *=);
-define_arithmetic_func!(div, /=);
-define_arithmetic_func!(modulo, %=);
+define_arithmetic_func!(sub, NumberOp::Sub);
+define_arithmetic_func!(mul, NumberOp::Mul);
+define_arithmetic_func!(div, NumberOp::Div);
+define_arithmetic_func!(modulo, NumberOp::Rem);
+
+define_arithmetic_assign_func!(sub_assign, NumberOp::Sub);
+define_arithmetic_assign_func!(mul_assign, NumberOp::Mul);
+define_arithmetic_assign_func!(div_assign, NumberOp::Div);
+define_arithmetic_assign_func!(modulo_assign, NumberOp::Rem);
+
+/// Bitwise ops work on Numbers (as 64 bit two's complement, or BigInts) and on Bytes, but the two
+/// can't be mixed
+fn bit_fold(args: Vec<DataRef>, name: &str, int_op: fn(i64, i64)->i64, big_op: fn(BigInt, BigInt)->BigInt, byte_op: fn(u8, u8)->u8, i: &mut Interpreter)->Result<DataRef> {
+    let mut iter = args.into_iter();
+    let Some(first) = iter.next() else {
+        bail!("`{name}` needs at least one argument");
+    };
+
+    let mut acc = match &*first.get_data() {
+        d@(Data::Number(_)|Data::BigInt(_)|Data::Byte(_))=>d.clone(),
+        _=>bail!("Type error: `{name}` only accepts Numbers and Bytes"),
+    };
+    for arg in iter {
+        acc = match (&acc, &*arg.get_data()) {
+            (Data::Number(l), Data::Number(r))=>Data::Number(int_op(*l, *r)),
+            (Data::Byte(l), Data::Byte(r))=>Data::Byte(byte_op(*l, *r)),
+            (Data::Byte(_), _)|(_, Data::Byte(_))=>bail!("Type error: `{name}` cannot mix Bytes and Numbers"),
+            (l, r)=>match (as_bigint(l), as_bigint(r)) {
+                (Some(l), Some(r))=>Data::from_bigint(big_op(l, r)),
+                _=>bail!("Type error: `{name}` only accepts Numbers and Bytes"),
+            },
+        };
+    }
+
+    return Ok(i.alloc(acc));
+}
+
+pub fn bit_and(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return bit_fold(args, "bit-and", |l, r|l & r, |l, r|l & r, |l, r|l & r, i);
+}
+
+pub fn bit_or(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return bit_fold(args, "bit-or", |l, r|l | r, |l, r|l | r, |l, r|l | r, i);
+}
+
+pub fn bit_xor(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return bit_fold(args, "bit-xor", |l, r|l ^ r, |l, r|l ^ r, |l, r|l ^ r, i);
+}
+
+pub fn bit_not(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data = match &*args[0].get_data() {
+        Data::Number(n)=>Data::Number(!n),
+        Data::BigInt(n)=>Data::from_bigint(!n),
+        Data::Byte(b)=>Data::Byte(!b),
+        _=>bail!("Type error: `bit-not` only accepts Numbers and Bytes"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+fn shift_amount(dr: &DataRef, name: &str)->Result<u32> {
+    match &*dr.get_data() {
+        Data::Number(n) if *n >= 0=>match u32::try_from(*n) {
+            Ok(n)=>Ok(n),
+            Err(_)=>bail!("`{name}` got a shift that is too big"),
+        },
+        Data::Number(_)=>bail!("`{name}` cannot shift by a negative amount"),
+        _=>bail!("Type error: `{name}` takes a Number as the shift"),
+    }
+}
+
+/// Numbers become a BigInt instead of losing bits. Bytes stay 8 bits.
+pub fn shift_left(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let amount = shift_amount(&args[1], "shift-left")?;
+    let data = match &*args[0].get_data() {
+        Data::Byte(b)=>Data::Byte(b.checked_shl(amount).unwrap_or(0)),
+        n@(Data::Number(_)|Data::BigInt(_))=>Data::from_bigint(as_bigint(n).unwrap() << amount),
+        _=>bail!("Type error: `shift-left` only accepts Numbers and Bytes"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+/// Keeps the sign of Numbers. Bytes are unsigned, so they are filled with zeros.
+pub fn shift_right(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let amount = shift_amount(&args[1], "shift-right")?;
+    let data = match &*args[0].get_data() {
+        Data::Byte(b)=>Data::Byte(b.checked_shr(amount).unwrap_or(0)),
+        Data::Number(n)=>Data::Number(n >> amount.min(63)),
+        Data::BigInt(n)=>Data::from_bigint(n >> amount),
+        _=>bail!("Type error: `shift-right` only accepts Numbers and Bytes"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+/// Fills with zeros, treating Numbers as 64 bits
+pub fn shift_right_logical(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let amount = shift_amount(&args[1], "shift-right-logical")?;
+    let data = match &*args[0].get_data() {
+        Data::Byte(b)=>Data::Byte(b.checked_shr(amount).unwrap_or(0)),
+        Data::Number(n)=>Data::Number((*n as u64).checked_shr(amount).unwrap_or(0) as i64),
+        Data::BigInt(_)=>bail!("`shift-right-logical` only works on Numbers that fit in 64 bits"),
+        _=>bail!("Type error: `shift-right-logical` only accepts Numbers and Bytes"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+/// Counts the leading zeros of the 64 bit Number or 8 bit Byte
+pub fn leading_zeros(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let count = match &*args[0].get_data() {
+        Data::Number(n)=>n.leading_zeros(),
+        Data::Byte(b)=>b.leading_zeros(),
+        Data::BigInt(_)=>bail!("`leading-zeros` only works on Numbers that fit in 64 bits"),
+        _=>bail!("Type error: `leading-zeros` only accepts Numbers and Bytes"),
+    };
+
+    return Ok(i.alloc(Data::Number(count as i64)));
+}
+
+/// Counts the set bits. Negative Numbers are counted as 64 bit two's complement.
+pub fn popcount(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let count = match &*args[0].get_data() {
+        Data::Number(n)=>n.count_ones() as u64,
+        Data::Byte(b)=>b.count_ones() as u64,
+        Data::BigInt(n) if n.is_negative()=>bail!("`popcount` of a negative BigInt is infinite"),
+        Data::BigInt(n)=>n.magnitude().count_ones(),
+        _=>bail!("Type error: `popcount` only accepts Numbers and Bytes"),
+    };
 
-define_arithmetic_assign_func!(sub_assign, -=);
-define_arithmetic_assign_func!(mul_assign, *=);
-define_arithmetic_assign_func!(div_assign, /=);
-define_arithmetic_assign_func!(modulo_assign, %=);
+    return Ok(i.alloc(Data::Number(count as i64)));
+}
+
+/// `div` and `rem` round towards negative infinity, so `(rem -7 2)` is 1 where `(% -7 2)` is -1
+fn floor_op(args: &[DataRef], name: &str, rem: bool)->Result<Data> {
+    let l = args[0].get_data();
+    let r = args[1].get_data();
+    match (&*l, &*r) {
+        (_, Data::Number(0)|Data::Byte(0))=>bail!("Division by zero"),
+        (Data::Byte(l), Data::Byte(r))=>Ok(Data::Byte(if rem {l % r} else {l / r})),
+        (Data::Byte(_), _)|(_, Data::Byte(_))=>bail!("Type error: `{name}` cannot mix Bytes and Numbers"),
+        // `i64::MIN / -1` overflows, so it goes through BigInt
+        (Data::Number(l), Data::Number(r)) if !(*l == i64::MIN && *r == -1)=>if rem {
+            Ok(Data::Number(Integer::mod_floor(l, r)))
+        } else {
+            Ok(Data::Number(Integer::div_floor(l, r)))
+        },
+        (l, r)=>match (as_bigint(l), as_bigint(r)) {
+            (Some(l), Some(r)) if rem=>Ok(Data::from_bigint(Integer::mod_floor(&l, &r))),
+            (Some(l), Some(r))=>Ok(Data::from_bigint(Integer::div_floor(&l, &r))),
+            _=>bail!("Type error: `{name}` only accepts Numbers and Bytes"),
+        },
+    }
+}
+
+pub fn floor_div(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data = floor_op(&args, "div", false)?;
+    return Ok(i.alloc(data));
+}
+
+pub fn floor_rem(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data = floor_op(&args, "rem", true)?;
+    return Ok(i.alloc(data));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(mut l: Data, r: Data, op: NumberOp)->Data {
+        number_op(&mut l, &r, op, "test").unwrap();
+        return l;
+    }
+
+    #[test]
+    fn overflow_promotes_to_bigint() {
+        let big_max = BigInt::from(i64::MAX);
+        let big_min = BigInt::from(i64::MIN);
+
+        assert_eq!(op(Data::Number(i64::MAX), Data::Number(1), NumberOp::Add), Data::BigInt(&big_max + 1));
+        assert_eq!(op(Data::Number(i64::MIN), Data::Number(1), NumberOp::Sub), Data::BigInt(&big_min - 1));
+        assert_eq!(op(Data::Number(i64::MAX), Data::Number(2), NumberOp::Mul), Data::BigInt(&big_max * 2));
+        assert_eq!(op(Data::Number(i64::MIN), Data::Number(-1), NumberOp::Div), Data::BigInt(-&big_min));
+    }
+
+    #[test]
+    fn results_that_fit_demote() {
+        let big = op(Data::Number(i64::MAX), Data::Number(1), NumberOp::Add);
+        assert_eq!(op(big, Data::Number(1), NumberOp::Sub), Data::Number(i64::MAX));
+
+        let big = op(Data::Number(i64::MIN), Data::Number(-1), NumberOp::Div);
+        assert_eq!(op(big, Data::Number(-1), NumberOp::Mul), Data::Number(i64::MIN));
+
+        // Rust's `%` overflows here, but the answer is still 0
+        assert_eq!(op(Data::Number(i64::MIN), Data::Number(-1), NumberOp::Rem), Data::Number(0));
+    }
+
+    #[test]
+    fn from_bigint_round_trip() {
+        for n in [0, 1, -1, i64::MAX, i64::MIN] {
+            assert_eq!(Data::from_bigint(BigInt::from(n)), Data::Number(n));
+        }
+
+        for big in [BigInt::from(i64::MAX) + 1, BigInt::from(i64::MIN) - 1] {
+            assert_eq!(Data::from_bigint(big.clone()), Data::BigInt(big.clone()));
+            assert_eq!(as_bigint(&Data::from_bigint(big.clone())), Some(big));
+        }
+    }
+
+    #[test]
+    fn floats_win() {
+        assert_eq!(op(Data::Number(1), Data::Float(0.5), NumberOp::Add), Data::Float(1.5));
+
+        let big = Data::BigInt(BigInt::from(i64::MAX) + 1);
+        assert_eq!(op(big, Data::Float(0.0), NumberOp::Add), Data::Float(i64::MAX as f64 + 1.0));
+    }
+
+    #[test]
+    fn integer_division_by_zero_errors() {
+        let mut n = Data::Number(1);
+        assert!(number_op(&mut n, &Data::Number(0), NumberOp::Div, "test").is_err());
+        assert!(number_op(&mut n, &Data::Number(0), NumberOp::Rem, "test").is_err());
+
+        let mut big = Data::BigInt(BigInt::from(i64::MAX) + 1);
+        assert!(number_op(&mut big, &Data::Number(0), NumberOp::Div, "test").is_err());
+    }
+
+    #[test]
+    fn mixed_numbers_order_by_value() {
+        let big = Data::BigInt(BigInt::from(i64::MAX) + 1);
+
+        assert_eq!(number_order(&Data::Number(1), &Data::Float(1.0)), Some(Ordering::Equal));
+        assert_eq!(number_order(&Data::Number(i64::MAX), &big), Some(Ordering::Less));
+        assert_eq!(number_order(&Data::Float(f64::NAN), &Data::Number(1)), None);
+    }
+
+    #[test]
+    fn floats_and_integers_compare_exactly() {
+        assert_eq!(number_order(&Data::Float(2.5), &Data::Number(2)), Some(Ordering::Greater));
+        assert_eq!(number_order(&Data::Number(3), &Data::Float(2.5)), Some(Ordering::Greater));
+        assert_eq!(number_order(&Data::Float(-2.5), &Data::Number(-2)), Some(Ordering::Less));
+        assert_eq!(number_order(&Data::Float(f64::INFINITY), &Data::Number(i64::MAX)), Some(Ordering::Greater));
+
+        // 2^53 + 1 rounds to 2^53 as a Float, but they are not equal
+        let n = (1i64 << 53) + 1;
+        assert_eq!(number_order(&Data::Float((1i64 << 53) as f64), &Data::Number(n)), Some(Ordering::Less));
+    }
+
+    /// Sets and maps have to agree with `=`
+    #[test]
+    fn equal_numbers_are_the_same_key() {
+        use crate::interpreter::data::SetKey;
+
+        let big = BigInt::from(i64::MAX) + 1;
+        let values = [
+            Data::Number(1),
+            Data::Number(0),
+            Data::Number((1i64 << 53) + 1),
+            Data::Float(1.0),
+            Data::Float(1.5),
+            Data::Float(-0.0),
+            Data::Float((1i64 << 53) as f64),
+            Data::Float(i64::MAX as f64 + 1.0),
+            Data::Float(f64::INFINITY),
+            Data::BigInt(BigInt::from(1)),
+            Data::BigInt(big.clone()),
+            Data::BigInt(big + 1),
+        ];
+
+        for l in values.iter() {
+            for r in values.iter() {
+                let equal = number_order(l, r) == Some(Ordering::Equal);
+                let same_key = SetKey::from_data(l).unwrap() == SetKey::from_data(r).unwrap();
+                assert_eq!(equal, same_key, "{l:?} and {r:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use std::cell::Ref;
+use crate::typed_array::{
+    TypedArray,
+    Element,
+    ElementOp,
+    Operand,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeFn,
+    ArgCount,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(make_f64, f64, 1),
+    builtin!(make_i64, i64, 1),
+    builtin!(make_u8, u8, 1),
+    builtin!(to_list, toList, 1),
+    builtin!(kind, 1),
+
+    builtin!(get, 2),
+    builtin!(set, 3),
+    builtin!(push, 2),
+    builtin!(slice, Any),
+
+    builtin!(add, 2),
+    builtin!(sub, 2),
+    builtin!(mul, 2),
+    builtin!(div, 2),
+
+    builtin!(sum, 1),
+    builtin!(dot, 2),
+    builtin!(min, 1),
+    builtin!(max, 1),
+];
+
+
+fn data_element(data: &Data)->Result<Element> {
+    match data {
+        Data::Float(f)=>Ok(Element::Float(*f)),
+        Data::Number(n)=>Ok(Element::Int(*n)),
+        Data::Byte(b)=>Ok(Element::Byte(*b)),
+        _=>bail!("Type error: Typed arrays can only hold Numbers, Floats, and Bytes"),
+    }
+}
+
+/// Borrows the array instead of copying it. Nothing can change it until the borrow is dropped.
+fn get_array<'a>(dr: &'a DataRef, name: &str)->Result<Ref<'a, TypedArray>> {
+    let arr = Ref::filter_map(dr.get_data(), |data|match data {
+        Data::TypedArray(arr)=>Some(arr),
+        _=>None,
+    });
+
+    match arr {
+        Ok(arr)=>Ok(arr),
+        Err(_)=>bail!("Type error: `{name}` only accepts typed arrays"),
+    }
+}
+
+/// Indices work the same as `std/list`, so negative ones count from the end
+fn array_index(dr: &DataRef, len: usize, allow_end: bool)->Result<usize> {
+    let n = match &*dr.get_data() {
+        Data::Number(n)=>*n,
+        _=>bail!("Type error: Arrays can only be indexed with a Number"),
+    };
+
+    let idx = if n < 0 {
+        n + len as i64
+    } else {
+        n
+    };
+    let max = if allow_end {len as i64} else {len as i64 - 1};
+    if idx < 0 || idx > max {
+        bail!("Index out of bounds");
+    }
+
+    return Ok(idx as usize);
+}
+
+/// Makes an array of the kind from a List or another typed array
+fn make_array(dr: &DataRef, kind: &str, i: &mut Interpreter)->Result<DataRef> {
+    let mut arr = TypedArray::new(kind)?;
+
+    match &*dr.get_data() {
+        Data::List(items)=>for item in items.iter() {
+            arr.push(data_element(&item.get_data())?)?;
+        },
+        Data::TypedArray(other)=>for element in other.iter() {
+            arr.push(element)?;
+        },
+        _=>bail!("Type error: `{kind}` only accepts a List or a typed array"),
+    }
+
+    return Ok(i.alloc(Data::TypedArray(arr)));
+}
+
+pub fn make_f64(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return make_array(&args[0], "f64", i);
+}
+
+pub fn make_i64(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return make_array(&args[0], "i64", i);
+}
+
+/// Numbers have to be between 0 and 255
+pub fn make_u8(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return make_array(&args[0], "u8", i);
+}
+
+pub fn to_list(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let arr = get_array(&args[0], "toList")?;
+    let list = arr.iter()
+        .map(|element|i.alloc(element.into()))
+        .collect();
+    drop(arr);
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+/// Gives the kind as a String: `"f64"`, `"i64"`, or `"u8"`
+pub fn kind(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let kind = match &*args[0].get_data() {
+        Data::TypedArray(arr)=>arr.kind(),
+        _=>bail!("Type error: `kind` only accepts typed arrays"),
+    };
+
+    return Ok(i.alloc(Data::String(kind.to_string())));
+}
+
+/// `(get arr idx)`
+pub fn get(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let element = match &*args[0].get_data() {
+        Data::TypedArray(arr)=>{
+            let idx = array_index(&args[1], arr.len(), false)?;
+            arr.get(idx).unwrap()
+        },
+        _=>bail!("Type error: `get` only accepts typed arrays"),
+    };
+
+    return Ok(i.alloc(element.into()));
+}
+
+/// `(set arr idx x)` replaces the element in place and returns the array
+pub fn set(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let element = data_element(&args[2].get_data())?;
+
+    let mut arr = args[0].clone();
+    let mut arr_ref = arr.try_get_data_mut()?;
+    match &mut *arr_ref {
+        Data::TypedArray(items)=>{
+            let idx = array_index(&args[1], items.len(), false)?;
+            items.set(idx, element)?;
+        },
+        _=>bail!("Type error: `set` only accepts typed arrays"),
+    }
+    drop(arr_ref);
+
+    return Ok(arr);
+}
+
+/// `(push arr x)` adds the element to the end and returns the array
+pub fn push(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let element = data_element(&args[1].get_data())?;
+
+    let mut arr = args[0].clone();
+    let mut arr_ref = arr.try_get_data_mut()?;
+    match &mut *arr_ref {
+        Data::TypedArray(items)=>items.push(element)?,
+        _=>bail!("Type error: `push` only accepts typed arrays"),
+    }
+    drop(arr_ref);
+
+    return Ok(arr);
+}
+
+/// `(slice arr start)` or `(slice arr start end)`, the same as `std/list/slice`
+pub fn slice(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 2 && args.len() != 3 {
+        bail!("`slice` takes an array, a start, and an optional end");
+    }
+
+    let arr = get_array(&args[0], "slice")?;
+    let start = array_index(&args[1], arr.len(), true)?;
+    let end = match args.get(2) {
+        Some(end)=>array_index(end, arr.len(), true)?,
+        None=>arr.len(),
+    };
+    if start > end {
+        bail!("Slice start is after the end");
+    }
+    let out = arr.slice(start, end)?;
+    drop(arr);
+
+    return Ok(i.alloc(Data::TypedArray(out)));
+}
+
+/// The second argument can be another array of the same kind and length, or a single number
+fn elementwise(args: &[DataRef], op: ElementOp, name: &str, i: &mut Interpreter)->Result<DataRef> {
+    let arr = get_array(&args[0], name)?;
+
+    let out = match &*args[1].get_data() {
+        Data::TypedArray(other)=>arr.elementwise(Operand::Array(other), op)?,
+        data=>arr.elementwise(Operand::Element(data_element(data)?), op)?,
+    };
+    drop(arr);
+
+    return Ok(i.alloc(Data::TypedArray(out)));
+}
+
+pub fn add(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return elementwise(&args, ElementOp::Add, "add", i);
+}
+
+pub fn sub(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return elementwise(&args, ElementOp::Sub, "sub", i);
+}
+
+pub fn mul(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return elementwise(&args, ElementOp::Mul, "mul", i);
+}
+
+/// Integer arrays use integer division and error on division by zero
+pub fn div(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return elementwise(&args, ElementOp::Div, "div", i);
+}
+
+/// Integer arrays give a Number and `f64` arrays give a Float
+pub fn sum(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let element = match &*args[0].get_data() {
+        Data::TypedArray(arr)=>arr.sum(),
+        _=>bail!("Type error: `sum` only accepts typed arrays"),
+    };
+
+    return Ok(i.alloc(element.into()));
+}
+
+/// Both arrays have to be the same kind and length
+pub fn dot(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let arr = get_array(&args[0], "dot")?;
+    let element = match &*args[1].get_data() {
+        Data::TypedArray(other)=>arr.dot(other)?,
+        _=>bail!("Type error: `dot` only accepts typed arrays"),
+    };
+    drop(arr);
+
+    return Ok(i.alloc(element.into()));
+}
+
+/// `None` if the array is empty
+pub fn min(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let element = match &*args[0].get_data() {
+        Data::TypedArray(arr)=>arr.min(),
+        _=>bail!("Type error: `min` only accepts typed arrays"),
+    };
+
+    return Ok(i.alloc(element.map(Data::from).unwrap_or(Data::None)));
+}
+
+/// `None` if the array is empty
+pub fn max(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let element = match &*args[0].get_data() {
+        Data::TypedArray(arr)=>arr.max(),
+        _=>bail!("Type error: `max` only accepts typed arrays"),
+    };
+
+    return Ok(i.alloc(element.map(Data::from).unwrap_or(Data::None)));
+}
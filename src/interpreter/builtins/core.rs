@@ -4,7 +4,7 @@ use anyhow::{
 };
 use super::{
     Interpreter,
-    Interner,
+    ConvertState,
     Data,
     DataRef,
     NativeFn,
@@ -26,17 +26,19 @@ pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
     builtin!(intern, 1),
     builtin!(fields, 1),
     builtin!(is_ident, isIdent, 1),
+    builtin!(freeze, 1),
+    builtin!(is_frozen, isFrozen, 1),
 ];
 
 
-pub fn is_ident(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn is_ident(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     match &*args[0].get_data() {
         Data::Ident(_)=>Ok(i.alloc(Data::Bool(true))),
         _=>Ok(i.alloc(Data::Bool(false))),
     }
 }
 
-pub fn fields(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn fields(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     match &*args[0].get_data() {
         Data::Object(fields)=>{
             let mut list = Vec::new();
@@ -54,12 +56,12 @@ pub fn fields(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result
     }
 }
 
-pub fn gc_collect(_args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn gc_collect(_args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     let count = i.gc_collect();
     return Ok(i.alloc(Data::Number(count as i64)));
 }
 
-pub fn and(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn and(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     for arg in args {
         match &*arg.get_data() {
             Data::Bool(true)=>{},
@@ -70,7 +72,7 @@ pub fn and(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<Da
     return Ok(i.alloc(Data::Bool(true)));
 }
 
-pub fn or(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn or(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     for arg in args {
         match &*arg.get_data() {
             Data::Bool(true)=>return Ok(i.alloc(Data::Bool(true))),
@@ -81,7 +83,7 @@ pub fn or(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<Dat
     return Ok(i.alloc(Data::Bool(false)));
 }
 
-pub fn index(mut args: Vec<DataRef>, _: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn index(mut args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     if args.len() != 2 {
         bail!("`index` only takes 2 arguments!");
     }
@@ -99,53 +101,74 @@ pub fn index(mut args: Vec<DataRef>, _: &mut Interpreter, _: &mut Interner)->Res
 
             return Ok(items[*i as usize].clone());
         },
-        (l, r)=>bail!("`index` can only index a list with a number. index: `{l:?}`, to_index: `{r:?}`"),
+        (Data::PVec(items), Data::Number(i))=>{
+            if *i < 0 || *i >= items.len() as i64 {
+                bail!("Index out of bounds");
+            }
+
+            return Ok(items.get(*i as usize).unwrap().clone());
+        },
+        (l, r)=>bail!("`index` can only index a list or vector with a number. index: `{l:?}`, to_index: `{r:?}`"),
     }
 }
 
-pub fn list(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn list(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     Ok(i.alloc(Data::List(args.into())))
 }
 
-pub fn clone(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn clone(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     Ok(i.clone_data(&args[0]))
 }
 
-pub fn length(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn length(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     if args.len() != 1 {bail!("Index only accepts one argument")}
 
     let data = args[0].get_data();
 
     match &*data {
         Data::List(items)=>Ok(i.alloc(Data::Number(items.len() as i64))),
-        Data::String(s)=>Ok(i.alloc(Data::Number(s.len() as i64))),
+        Data::String(s)=>Ok(i.alloc(Data::Number(s.chars().count() as i64))),
+        Data::Set(items)=>Ok(i.alloc(Data::Number(items.len() as i64))),
+        Data::PVec(items)=>Ok(i.alloc(Data::Number(items.len() as i64))),
+        Data::PMap(map)=>Ok(i.alloc(Data::Number(map.len() as i64))),
+        Data::TypedArray(arr)=>Ok(i.alloc(Data::Number(arr.len() as i64))),
         _=>Ok(i.alloc(Data::Number(0))),
     }
 }
 
-pub fn list_pop(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn list_pop(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     let mut data = args[0].clone();
-    let mut data_ref = data.get_data_mut();
+    let mut data_ref = data.try_get_data_mut()?;
     match &mut *data_ref {
         Data::List(items)=>return Ok(items.pop().unwrap_or_else(||i.alloc(Data::None))),
         _=>bail!("Type error: `listPop` only accepts Lists"),
     }
 }
 
-pub fn debug(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+/// Makes the value and everything it references read-only, then returns it
+pub fn freeze(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    args[0].freeze();
+    return Ok(args[0].clone());
+}
+
+pub fn is_frozen(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    Ok(i.alloc(Data::Bool(args[0].is_frozen())))
+}
+
+pub fn debug(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     eprintln!("{args:#?}");
     return Ok(i.alloc(Data::None));
 }
 
-pub fn intern(args: Vec<DataRef>, i: &mut Interpreter, interner: &mut Interner)->Result<DataRef> {
+pub fn intern(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     let dr_ref = args[0].get_data();
     match &*dr_ref {
         Data::String(s)=>{
-            let ident = interner.intern(s.as_str());
+            let ident = state.interner.intern(s.as_str());
             return Ok(i.alloc(Data::Ident(ident)));
         },
         Data::Ident(ident)=>{
-            let s = interner.get(*ident).to_string();
+            let s = state.interner.get(*ident).to_string();
             return Ok(i.alloc(Data::String(s)));
         },
         _=>bail!("Type error: `intern` can only accept String or Ident"),
@@ -0,0 +1,429 @@
+use anyhow::{
+    Error,
+    Result,
+    anyhow,
+    bail,
+};
+use regex::Regex;
+use std::{
+    fs::{
+        self,
+        OpenOptions,
+    },
+    io::{
+        Error as IoError,
+        ErrorKind as IoErrorKind,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::UNIX_EPOCH,
+    env,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    ObjectMap,
+    NativeFn,
+    ArgCount,
+    random::Rng,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(exists, 1),
+    builtin!(is_dir, isDir, 1),
+    builtin!(is_file, isFile, 1),
+    builtin!(metadata, 1),
+    builtin!(list, 1),
+    builtin!(walk, Any),
+
+    builtin!(create_file, createFile, 1),
+    builtin!(create_dir, createDir, 1),
+    builtin!(remove, 1),
+    builtin!(remove_all, removeAll, 1),
+    builtin!(rename, 2),
+    builtin!(copy, 2),
+
+    builtin!(temp_file, tempFile, Any),
+    builtin!(temp_dir, tempDir, Any),
+];
+
+
+/// Every `std/fs` error message starts with the kind of error and then the path, like
+/// ``Not found: `a.txt` ``. The kinds are `Not found`, `Permission denied`, `Already exists`, and
+/// `Filesystem error at`, which also has the reason at the end.
+fn fs_error(source: IoError, path: &str)->Error {
+    match source.kind() {
+        IoErrorKind::NotFound=>anyhow!("Not found: `{path}`"),
+        IoErrorKind::PermissionDenied=>anyhow!("Permission denied: `{path}`"),
+        IoErrorKind::AlreadyExists=>anyhow!("Already exists: `{path}`"),
+        _=>anyhow!("Filesystem error at `{path}`: {source}"),
+    }
+}
+
+
+fn get_path(dr: &DataRef, name: &str)->Result<String> {
+    match &*dr.get_data() {
+        Data::String(s)=>Ok(s.clone()),
+        _=>bail!("Type error: `{name}` only accepts String paths"),
+    }
+}
+
+fn path_string(path: &Path)->String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Makes `(object .name .path .isDir)` for a directory entry
+fn entry_object(path: &Path, is_dir: bool, i: &mut Interpreter, state: &mut ConvertState)->DataRef {
+    let name = path.file_name()
+        .map(|name|name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut fields = ObjectMap::default();
+    fields.insert(state.intern("name"), i.alloc(Data::String(name)));
+    fields.insert(state.intern("path"), i.alloc(Data::String(path_string(path))));
+    fields.insert(state.intern("isDir"), i.alloc(Data::Bool(is_dir)));
+
+    return i.alloc(Data::Object(fields));
+}
+
+/// The entries of a directory, sorted by name
+fn read_dir(path: &Path)->Result<Vec<(PathBuf, bool)>> {
+    let path_str = path_string(path);
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(path).map_err(|e|fs_error(e, &path_str))? {
+        let entry = entry.map_err(|e|fs_error(e, &path_str))?;
+        let file_type = entry.file_type().map_err(|e|fs_error(e, &path_str))?;
+        entries.push((entry.path(), file_type.is_dir()));
+    }
+    entries.sort();
+
+    return Ok(entries);
+}
+
+/// Turns a glob into a regex that matches the whole path. `*` and `?` don't match `/`, `**`
+/// matches any number of directories, and `[...]` is a set of chars like in a regex.
+fn glob_regex(glob: &str)->Result<Regex> {
+    let mut out = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*')=>{
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    out.push_str("(?:.*/)?");
+                } else {
+                    out.push_str(".*");
+                }
+            },
+            '*'=>out.push_str("[^/]*"),
+            '?'=>out.push_str("[^/]"),
+            '['=>{
+                out.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    out.push('^');
+                }
+                loop {
+                    match chars.next() {
+                        Some(']')=>break,
+                        Some('\\')=>out.push_str("\\\\"),
+                        Some(c)=>out.push(c),
+                        None=>bail!("Unclosed `[` in glob {glob:?}"),
+                    }
+                }
+                out.push(']');
+            },
+            c=>out.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    out.push('$');
+
+    return Ok(Regex::new(&out)?);
+}
+
+pub fn exists(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "exists")?;
+    let exists = Path::new(&path).try_exists().map_err(|e|fs_error(e, &path))?;
+
+    return Ok(i.alloc(Data::Bool(exists)));
+}
+
+/// `false` if the path doesn't exist
+pub fn is_dir(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "isDir")?;
+    return Ok(i.alloc(Data::Bool(Path::new(&path).is_dir())));
+}
+
+/// `false` if the path doesn't exist
+pub fn is_file(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "isFile")?;
+    return Ok(i.alloc(Data::Bool(Path::new(&path).is_file())));
+}
+
+/// Gives `(object .size .mtime .isDir .isFile .isSymlink .readonly)`. `mtime` is seconds since the
+/// Unix epoch, the same as `std/time/timestamp`.
+pub fn metadata(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "metadata")?;
+    let is_symlink = fs::symlink_metadata(&path)
+        .map_err(|e|fs_error(e, &path))?
+        .is_symlink();
+    let meta = fs::metadata(&path).map_err(|e|fs_error(e, &path))?;
+
+    let mtime = match meta.modified().map_err(|e|fs_error(e, &path))?.duration_since(UNIX_EPOCH) {
+        Ok(d)=>d.as_secs_f64(),
+        Err(e)=>-e.duration().as_secs_f64(),
+    };
+
+    let mut fields = ObjectMap::default();
+    fields.insert(state.intern("size"), i.alloc(Data::Number(meta.len() as i64)));
+    fields.insert(state.intern("mtime"), i.alloc(Data::Float(mtime)));
+    fields.insert(state.intern("isDir"), i.alloc(Data::Bool(meta.is_dir())));
+    fields.insert(state.intern("isFile"), i.alloc(Data::Bool(meta.is_file())));
+    fields.insert(state.intern("isSymlink"), i.alloc(Data::Bool(is_symlink)));
+    fields.insert(state.intern("readonly"), i.alloc(Data::Bool(meta.permissions().readonly())));
+
+    return Ok(i.alloc(Data::Object(fields)));
+}
+
+/// Gives a list of `(object .name .path .isDir)` for the directory, sorted by name
+pub fn list(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "list")?;
+    let list = read_dir(Path::new(&path))?
+        .into_iter()
+        .map(|(path, is_dir)|entry_object(&path, is_dir, i, state))
+        .collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+/// `(walk root)` or `(walk root glob)` gives every entry under `root`, the same as `list`. The glob
+/// is matched against the path relative to `root`, like `**/*.slp`. Symlinked directories are not
+/// followed.
+pub fn walk(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let (root, glob) = match args.as_slice() {
+        [root]=>(get_path(root, "walk")?, None),
+        [root, glob]=>(get_path(root, "walk")?, Some(glob_regex(&get_path(glob, "walk")?)?)),
+        _=>bail!("`walk` takes a root and an optional glob"),
+    };
+    let root = Path::new(&root);
+
+    let mut out = Vec::new();
+    let mut todo = vec![root.to_path_buf()];
+    while let Some(dir) = todo.pop() {
+        // reversed so the directories are walked in order
+        for (path, is_dir) in read_dir(&dir)?.into_iter().rev() {
+            if is_dir {
+                todo.push(path.clone());
+            }
+
+            let matches = match &glob {
+                Some(glob)=>{
+                    let rel = path.strip_prefix(root).unwrap_or(&path);
+                    let rel = rel.components()
+                        .map(|c|c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    glob.is_match(&rel)
+                },
+                None=>true,
+            };
+            if matches {
+                out.push((path, is_dir));
+            }
+        }
+    }
+    out.sort();
+
+    let list = out.into_iter()
+        .map(|(path, is_dir)|entry_object(&path, is_dir, i, state))
+        .collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+/// Makes an empty file. Errors if it already exists.
+pub fn create_file(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "createFile")?;
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e|fs_error(e, &path))?;
+
+    return Ok(i.alloc(Data::None));
+}
+
+/// Makes the directory and any missing parents. Errors if it already exists.
+pub fn create_dir(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "createDir")?;
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent).map_err(|e|fs_error(e, &path))?;
+    }
+    // `create_dir` fails if the directory is already there, so there is no gap for something else
+    // to make it between checking and creating
+    fs::create_dir(&path).map_err(|e|fs_error(e, &path))?;
+
+    return Ok(i.alloc(Data::None));
+}
+
+/// Removes a file or an empty directory
+pub fn remove(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "remove")?;
+    let meta = fs::symlink_metadata(&path).map_err(|e|fs_error(e, &path))?;
+    if meta.is_dir() {
+        fs::remove_dir(&path).map_err(|e|fs_error(e, &path))?;
+    } else {
+        fs::remove_file(&path).map_err(|e|fs_error(e, &path))?;
+    }
+
+    return Ok(i.alloc(Data::None));
+}
+
+/// Removes a file, or a directory and everything in it
+pub fn remove_all(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let path = get_path(&args[0], "removeAll")?;
+    let meta = fs::symlink_metadata(&path).map_err(|e|fs_error(e, &path))?;
+    if meta.is_dir() {
+        fs::remove_dir_all(&path).map_err(|e|fs_error(e, &path))?;
+    } else {
+        fs::remove_file(&path).map_err(|e|fs_error(e, &path))?;
+    }
+
+    return Ok(i.alloc(Data::None));
+}
+
+/// `(rename from to)`. Replaces `to` if it is a file.
+pub fn rename(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let from = get_path(&args[0], "rename")?;
+    let to = get_path(&args[1], "rename")?;
+    fs::rename(&from, &to).map_err(|e|{
+        // If `from` is there, it was `to` that was the problem
+        let path = if fs::symlink_metadata(&from).is_ok() {&to} else {&from};
+        fs_error(e, path)
+    })?;
+
+    return Ok(i.alloc(Data::None));
+}
+
+/// `(copy from to)` copies a file and gives the number of bytes copied
+pub fn copy(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let from = get_path(&args[0], "copy")?;
+    let to = get_path(&args[1], "copy")?;
+    let len = fs::copy(&from, &to).map_err(|e|{
+        // If `from` is a file that can be read, it was `to` that was the problem
+        let from_ok = fs::File::open(&from)
+            .and_then(|file|file.metadata())
+            .is_ok_and(|meta|meta.is_file());
+        let path = if from_ok {&to} else {&from};
+        fs_error(e, path)
+    })?;
+
+    return Ok(i.alloc(Data::Number(len as i64)));
+}
+
+/// Picks an unused path in the system temp directory and calls `make` on it until it works
+fn make_temp(args: &[DataRef], name: &str, make: fn(&Path)->std::io::Result<()>, i: &mut Interpreter)->Result<DataRef> {
+    let prefix = match args {
+        []=>String::from("tmp"),
+        [prefix]=>get_path(prefix, name)?,
+        _=>bail!("`{name}` takes an optional name prefix"),
+    };
+
+    let dir = env::temp_dir();
+    let mut rng = Rng::from_entropy();
+    loop {
+        let path = dir.join(format!("{prefix}-{:016x}", rng.next_u64()));
+        match make(&path) {
+            Ok(())=>return Ok(i.alloc(Data::String(path_string(&path)))),
+            Err(e) if e.kind() == IoErrorKind::AlreadyExists=>continue,
+            Err(e)=>return Err(fs_error(e, &path_string(&path))),
+        }
+    }
+}
+
+/// Makes a new empty file in the system temp directory and gives its path. It is not removed
+/// automatically.
+pub fn temp_file(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return make_temp(&args, "tempFile", |path|{
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map(drop)
+    }, i);
+}
+
+/// Same as `tempFile`, but makes a directory
+pub fn temp_dir(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return make_temp(&args, "tempDir", |path|fs::create_dir(path), i);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(glob: &str, path: &str)->bool {
+        glob_regex(glob).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn glob_stars() {
+        assert!(matches("*.slp", "main.slp"));
+        assert!(matches("*.slp", ".slp"));
+        assert!(!matches("*.slp", "src/main.slp"));
+        assert!(!matches("*.slp", "main.slp.bak"));
+
+        assert!(matches("**/*.slp", "main.slp"));
+        assert!(matches("**/*.slp", "src/a/b/main.slp"));
+        assert!(!matches("**/*.slp", "src/main.rs"));
+
+        assert!(matches("src/**", "src/a/b.slp"));
+        assert!(!matches("src/**", "lib/a.slp"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "a/xb"));
+    }
+
+    #[test]
+    fn glob_single_chars() {
+        assert!(matches("?.rs", "a.rs"));
+        assert!(!matches("?.rs", "ab.rs"));
+        assert!(!matches("a?b", "a/b"));
+
+        assert!(matches("[ab].txt", "a.txt"));
+        assert!(!matches("[ab].txt", "c.txt"));
+        assert!(matches("[!ab].txt", "c.txt"));
+        assert!(!matches("[!ab].txt", "a.txt"));
+        assert!(matches("[0-9].txt", "7.txt"));
+    }
+
+    #[test]
+    fn glob_escapes_regex_chars() {
+        assert!(matches("a+b.txt", "a+b.txt"));
+        assert!(!matches("a+b.txt", "aab.txt"));
+        assert!(!matches("a.txt", "aXtxt"));
+        assert!(matches("(x)|{y}$", "(x)|{y}$"));
+    }
+
+    #[test]
+    fn glob_errors() {
+        assert!(glob_regex("[ab").is_err());
+    }
+
+    #[test]
+    fn error_messages_start_with_the_kind() {
+        let msg = |kind: IoErrorKind|fs_error(IoError::from(kind), "a.txt").to_string();
+
+        assert_eq!(msg(IoErrorKind::NotFound), "Not found: `a.txt`");
+        assert_eq!(msg(IoErrorKind::PermissionDenied), "Permission denied: `a.txt`");
+        assert_eq!(msg(IoErrorKind::AlreadyExists), "Already exists: `a.txt`");
+        assert!(msg(IoErrorKind::Interrupted).starts_with("Filesystem error at `a.txt`: "));
+    }
+}
@@ -7,16 +7,18 @@ use std::{
         Read,
         Write,
         BufReader,
+        BufWriter,
         BufRead,
         stdout,
+        stderr,
     },
     rc::Rc,
     cell::RefCell,
-    fs::File,
+    fs::OpenOptions,
 };
 use super::{
     Interpreter,
-    Interner,
+    ConvertState,
     Data,
     DataRef,
     NativeData,
@@ -27,45 +29,93 @@ use super::{
 
 
 pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
-    builtin!(open, 1),
+    builtin!(open, Any),
     builtin!(read_line, readLine, 1),
     builtin!(read, 1),
     builtin!(write, 2),
+    builtin!(flush, 1),
+    builtin!(close, 1),
 ];
 
 
-pub fn open(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
-    let data_ref = args[0].get_data();
-    match &*data_ref {
-        Data::String(s)=>{
-            let file = File::open(s)?;
-            println!("Open `{s}`");
-
-            // hehehehe... triangle of `new`
-            return Ok(
-                i.alloc(
-                    Data::NativeData(
-                        NativeData::File(
-                            Rc::new(
-                                RefCell::new(
-                                    BufReader::new(file)
-                                )
-                            )
-                        )
-                    )
-                )
-            );
-        },
+/// `(open path modes...)`. The modes are `.read`, `.write`, `.append`, and `.create`, and the
+/// default is `.read`. `.write` truncates the file, and `.create` makes it if it doesn't exist.
+/// Files can be opened for reading or writing, but not both, and `.write` and `.append` can't be
+/// used together.
+pub fn open(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let Some((path, modes)) = args.split_first() else {
+        bail!("`open` takes a path and optional modes");
+    };
+    let path = match &*path.get_data() {
+        Data::String(s)=>s.clone(),
         _=>bail!("Open can only take Strings"),
+    };
+
+    let mut options = OpenOptions::new();
+    let mut read = false;
+    let mut write = false;
+    let mut truncate = false;
+    let mut append = false;
+    let mut create = false;
+    for mode in modes {
+        let mode = match &*mode.get_data() {
+            Data::Ident(mode)=>*mode,
+            _=>bail!("Type error: `open` modes must be Idents like `.read` or `.write`"),
+        };
+        match state.interner.get(mode) {
+            "read"=>{
+                read = true;
+                options.read(true);
+            },
+            "write"=>{
+                write = true;
+                truncate = true;
+                options.write(true).truncate(true);
+            },
+            "append"=>{
+                write = true;
+                append = true;
+                options.append(true);
+            },
+            "create"=>{
+                create = true;
+                options.create(true);
+            },
+            mode=>bail!("Unknown `open` mode `.{mode}`. Expected `.read`, `.write`, `.append`, or `.create`"),
+        }
+    }
+    if read && write {
+        bail!("`open` cannot open a file for reading and writing at once");
+    }
+    if truncate && append {
+        bail!("`open` cannot use `.write` and `.append` together. `.write` replaces the file and `.append` adds to the end");
+    }
+    if create && !write {
+        bail!("`open` needs `.write` or `.append` to use `.create`");
+    }
+    if !write {
+        options.read(true);
     }
+
+    let file = options.open(&path)?;
+    let data = if write {
+        NativeData::WriteFile(Rc::new(RefCell::new(Some(BufWriter::new(file)))))
+    } else {
+        NativeData::File(Rc::new(RefCell::new(Some(BufReader::new(file)))))
+    };
+
+    return Ok(i.alloc(Data::NativeData(data)));
 }
 
-pub fn read_line(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn read_line(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     let data_ref = args[0].get_data();
     match &*data_ref {
         Data::NativeData(d)=>match d {
             NativeData::File(f)=>{
                 let mut file = f.borrow_mut();
+                let Some(file) = file.as_mut() else {
+                    bail!("Cannot read from a closed file");
+                };
                 let mut buf = String::new();
                 file.read_line(&mut buf)?;
                 while buf.ends_with(|c:char|c=='\r'||c=='\n') {
@@ -84,20 +134,26 @@ pub fn read_line(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Res
 
                 return Ok(i.alloc(Data::String(buf)));
             },
+            NativeData::WriteFile(_)=>bail!("Cannot read from a file opened for writing"),
             NativeData::Stdout=>bail!("Cannot read from stdout"),
+            NativeData::Stderr=>bail!("Cannot read from stderr"),
+            NativeData::Regex(_)=>bail!("Cannot read from a regex"),
+            NativeData::Random(_)=>bail!("Cannot read from a random generator"),
         },
         _=>bail!("Invalid type for `read`"),
     }
 }
 
-pub fn read(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn read(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     let data = &args[0];
     let data_ref = data.get_data();
     match &*data_ref {
         Data::NativeData(d)=>match d {
             NativeData::File(f)=>{
-                println!("Read a file");
                 let mut file = f.borrow_mut();
+                let Some(file) = file.as_mut() else {
+                    bail!("Cannot read from a closed file");
+                };
                 let mut buf = String::new();
 
                 file.read_to_string(&mut buf)?;
@@ -105,7 +161,6 @@ pub fn read(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<D
                 return Ok(i.alloc(Data::String(buf)));
             },
             NativeData::Stdin(file_lock)=>{
-                println!("Read stdin");
                 let mut file = file_lock.borrow_mut();
                 let mut buf = String::new();
 
@@ -113,13 +168,19 @@ pub fn read(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<D
 
                 return Ok(i.alloc(Data::String(buf)));
             },
+            NativeData::WriteFile(_)=>bail!("Cannot read from a file opened for writing"),
             NativeData::Stdout=>bail!("Cannot read from stdout"),
+            NativeData::Stderr=>bail!("Cannot read from stderr"),
+            NativeData::Regex(_)=>bail!("Cannot read from a regex"),
+            NativeData::Random(_)=>bail!("Cannot read from a random generator"),
         },
         _=>bail!("Invalid type for `read`"),
     }
 }
 
-pub fn write(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+/// Writes to files are buffered until `flush`, `close`, or the file is collected. Stdout and
+/// stderr are flushed right away.
+pub fn write(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     let file_ref = args[0].get_data();
     let data_ref = args[1].get_data();
     let data = match &*data_ref {
@@ -128,12 +189,14 @@ pub fn write(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<
     };
     match &*file_ref {
         Data::NativeData(d)=>match d {
-            NativeData::File(f)=>{
+            NativeData::WriteFile(f)=>{
                 let mut file = f.borrow_mut();
-                let len = file.get_mut().write(data.as_bytes())?;
-                file.get_mut().flush()?;
+                let Some(file) = file.as_mut() else {
+                    bail!("Cannot write to a closed file");
+                };
+                file.write_all(data.as_bytes())?;
 
-                return Ok(i.alloc(Data::Number(len as i64)));
+                return Ok(i.alloc(Data::Number(data.len() as i64)));
             },
             NativeData::Stdout=>{
                 let mut file = stdout();
@@ -142,8 +205,52 @@ pub fn write(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<
 
                 return Ok(i.alloc(Data::Number(len as i64)));
             },
+            NativeData::Stderr=>{
+                let mut file = stderr();
+                let len = file.write(data.as_bytes())?;
+                file.flush()?;
+
+                return Ok(i.alloc(Data::Number(len as i64)));
+            },
+            NativeData::File(_)=>bail!("Cannot write to a file opened for reading"),
             NativeData::Stdin(_)=>bail!("Cannot write to stdin"),
+            NativeData::Regex(_)=>bail!("Cannot write to a regex"),
+            NativeData::Random(_)=>bail!("Cannot write to a random generator"),
         },
-        _=>bail!("Invalid type for `read`"),
+        _=>bail!("Invalid type for `write`"),
+    }
+}
+
+/// Does nothing for things that can't be written to
+pub fn flush(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    match &*args[0].get_data() {
+        Data::NativeData(NativeData::WriteFile(f))=>{
+            let mut file = f.borrow_mut();
+            let Some(file) = file.as_mut() else {
+                bail!("Cannot flush a closed file");
+            };
+            file.flush()?;
+        },
+        Data::NativeData(NativeData::Stdout)=>stdout().flush()?,
+        Data::NativeData(NativeData::Stderr)=>stderr().flush()?,
+        Data::NativeData(_)=>{},
+        _=>bail!("Invalid type for `flush`"),
     }
+
+    return Ok(i.alloc(Data::None));
+}
+
+/// Flushes and closes a file. Every copy of the file is closed, and closing it again does nothing.
+pub fn close(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    match &*args[0].get_data() {
+        Data::NativeData(NativeData::WriteFile(f))=>{
+            if let Some(mut file) = f.borrow_mut().take() {
+                file.flush()?;
+            }
+        },
+        Data::NativeData(NativeData::File(f))=>drop(f.borrow_mut().take()),
+        _=>bail!("Type error: `close` only accepts files"),
+    }
+
+    return Ok(i.alloc(Data::None));
 }
@@ -0,0 +1,92 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeIter,
+    NativeFn,
+    ArgCount,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(iter, 1),
+    builtin!(next, 1),
+    builtin!(is_done, isDone, 1),
+    builtin!(range, Any),
+    builtin!(collect, 1),
+];
+
+
+/// Gets an iterator for lists, strings, sets, vectors, maps, objects, and functions
+pub fn iter(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    return i.make_iter(&args[0], state);
+}
+
+/// Gives the next item, or `done`
+pub fn next(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    match i.iter_next(&args[0], state)? {
+        Some(item)=>Ok(item),
+        None=>Ok(i.alloc(Data::Done)),
+    }
+}
+
+pub fn is_done(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let done = matches!(&*args[0].get_data(), Data::Done);
+
+    return Ok(i.alloc(Data::Bool(done)));
+}
+
+/// `(range end)`, `(range start end)`, or `(range start end step)`. The end is not included. The
+/// end can be `None` to go on forever, and `(range)` counts up from 0 forever.
+pub fn range(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut nums = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        match &*arg.get_data() {
+            Data::Number(n)=>nums.push(Some(*n)),
+            Data::None=>nums.push(None),
+            _=>bail!("Type error: `range` only accepts Numbers and None"),
+        }
+    }
+
+    let (start, end, step) = match nums.as_slice() {
+        []=>(Some(0), None, Some(1)),
+        [end]=>(Some(0), *end, Some(1)),
+        [start, end]=>(*start, *end, Some(1)),
+        [start, end, step]=>(*start, *end, *step),
+        _=>bail!("`range` takes an end, a start and end, or a start, end, and step"),
+    };
+    let (Some(start), Some(step)) = (start, step) else {
+        bail!("Only the end of a `range` can be None");
+    };
+    if step == 0 {
+        bail!("`range` cannot have a step of 0");
+    }
+
+    return Ok(i.alloc(Data::Iterator(NativeIter::Range {
+        next: Some(start),
+        end,
+        step,
+    })));
+}
+
+/// Runs the iterator to the end and puts the items in a list
+pub fn collect(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let it = i.make_iter(&args[0], state)?.external();
+
+    // the items are only held by us until the list is made
+    let mut items = Vec::new();
+    while let Some(item) = i.iter_next(&it, state)? {
+        items.push(item.external());
+    }
+
+    let list = items.into_iter()
+        .map(|item|item.inner())
+        .collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
@@ -0,0 +1,134 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeIter,
+    LazySeq,
+    LazySource,
+    NativeFn,
+    ArgCount,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(lazy_seq, lazySeq, 1),
+    builtin!(iterate, 2),
+    builtin!(repeat, Any),
+    builtin!(take, 2),
+    builtin!(skip, drop, 2),
+    builtin!(take_while, takeWhile, 2),
+];
+
+
+/// Wraps the iterator in a lazy sequence so its items are cached as they are realized
+pub fn seq_of(it: DataRef, i: &mut Interpreter)->DataRef {
+    i.alloc(Data::LazySeq(LazySeq::new(LazySource::Iter(it))))
+}
+
+/// Lazy sequences already cache their items, so `take` and `drop` read from them instead of
+/// wrapping them in another cache
+fn slice_of(seq: &DataRef, start: usize, len: Option<usize>, i: &mut Interpreter)->Option<DataRef> {
+    if !matches!(&*seq.get_data(), Data::LazySeq(_)) {
+        return None;
+    }
+
+    let slice = LazySource::Slice {
+        seq: seq.clone(),
+        start,
+        len,
+    };
+    return Some(i.alloc(Data::LazySeq(LazySeq::new(slice))));
+}
+
+fn count_arg(dr: &DataRef, name: &str)->Result<usize> {
+    match &*dr.get_data() {
+        Data::Number(n) if *n >= 0=>Ok(*n as usize),
+        Data::Number(_)=>bail!("`{name}` cannot take a negative count"),
+        _=>bail!("Type error: `{name}` takes a Number as the count"),
+    }
+}
+
+/// `(lazySeq f)` calls `f` the first time an item is needed. It should give something that can be
+/// iterated over.
+pub fn lazy_seq(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let thunk = args[0].clone();
+
+    return Ok(i.alloc(Data::LazySeq(LazySeq::new(LazySource::Thunk(thunk)))));
+}
+
+/// `(iterate f x)` is the infinite sequence `x`, `(f x)`, `(f (f x))`, ...
+pub fn iterate(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let it = i.alloc(Data::Iterator(NativeIter::Iterate {
+        func: args[0].clone(),
+        cur: args[1].clone(),
+        started: false,
+    }));
+
+    return Ok(seq_of(it, i));
+}
+
+/// `(repeat x)` forever, or `(repeat x n)` times
+pub fn repeat(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let remaining = match args.len() {
+        1=>None,
+        2=>Some(count_arg(&args[1], "repeat")?),
+        _=>bail!("`repeat` takes an item and an optional count"),
+    };
+
+    let it = i.alloc(Data::Iterator(NativeIter::Repeat {
+        item: args[0].clone(),
+        remaining,
+    }));
+
+    return Ok(seq_of(it, i));
+}
+
+/// `(take coll n)`
+pub fn take(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let remaining = count_arg(&args[1], "take")?;
+    if let Some(slice) = slice_of(&args[0], 0, Some(remaining), i) {
+        return Ok(slice);
+    }
+    let inner = i.make_iter(&args[0], state)?;
+
+    let it = i.alloc(Data::Iterator(NativeIter::Take {
+        inner,
+        remaining,
+    }));
+
+    return Ok(seq_of(it, i));
+}
+
+/// `(drop coll n)`
+pub fn skip(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let count = count_arg(&args[1], "drop")?;
+    if let Some(slice) = slice_of(&args[0], count, None, i) {
+        return Ok(slice);
+    }
+    let inner = i.make_iter(&args[0], state)?;
+
+    let it = i.alloc(Data::Iterator(NativeIter::Skip {
+        inner,
+        count,
+    }));
+
+    return Ok(seq_of(it, i));
+}
+
+/// `(takeWhile coll pred)` stops at the first item that `pred` gives `false` for
+pub fn take_while(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let inner = i.make_iter(&args[0], state)?;
+
+    let it = i.alloc(Data::Iterator(NativeIter::TakeWhile {
+        inner,
+        pred: args[1].clone(),
+        done: false,
+    }));
+
+    return Ok(seq_of(it, i));
+}
@@ -0,0 +1,255 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeFn,
+    ArgCount,
+    arithmetic::data_eq,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    // in place
+    builtin!(push, Any),
+    builtin!(pop, 1),
+    builtin!(insert, 3),
+    builtin!(remove, 2),
+    builtin!(set, 3),
+    builtin!(extend, Any),
+    builtin!(clear, 1),
+    builtin!(reverse_in_place, reverseInPlace, 1),
+
+    // copying
+    builtin!(get, 2),
+    builtin!(concat, Any),
+    builtin!(slice, Any),
+    builtin!(reverse, 1),
+    builtin!(contains, 2),
+    builtin!(index_of, indexOf, 2),
+];
+
+
+/// Negative indices count from the end. `allow_end` is for things like `insert` where the index
+/// just past the last item is fine.
+fn list_index(dr: &DataRef, len: usize, allow_end: bool)->Result<usize> {
+    let n = match &*dr.get_data() {
+        Data::Number(n)=>*n,
+        _=>bail!("Type error: Lists can only be indexed with a Number"),
+    };
+
+    let idx = if n < 0 {
+        n + len as i64
+    } else {
+        n
+    };
+    let max = if allow_end {len as i64} else {len as i64 - 1};
+    if idx < 0 || idx > max {
+        bail!("Index out of bounds");
+    }
+
+    return Ok(idx as usize);
+}
+
+/// Clones the items out so nothing is borrowed while we call back into the script
+fn get_items(dr: &DataRef, name: &str)->Result<Vec<DataRef>> {
+    match &*dr.get_data() {
+        Data::List(items)=>Ok(items.clone()),
+        _=>bail!("Type error: `{name}` only accepts Lists"),
+    }
+}
+
+/// `(push list items...)` adds the items to the end and returns the list
+pub fn push(mut args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.is_empty() {
+        bail!("`push` needs a list");
+    }
+
+    let mut list = args.remove(0);
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>items.extend(args),
+        _=>bail!("Type error: `push` only accepts Lists"),
+    }
+    drop(list_ref);
+
+    return Ok(list);
+}
+
+/// Removes the last item, or gives `None` if the list is empty
+pub fn pop(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut list = args[0].clone();
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>return Ok(items.pop().unwrap_or_else(||i.alloc(Data::None))),
+        _=>bail!("Type error: `pop` only accepts Lists"),
+    }
+}
+
+/// `(insert list idx item)` puts the item before the index and returns the list
+pub fn insert(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut list = args[0].clone();
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>{
+            let idx = list_index(&args[1], items.len(), true)?;
+            items.insert(idx, args[2].clone());
+        },
+        _=>bail!("Type error: `insert` only accepts Lists"),
+    }
+    drop(list_ref);
+
+    return Ok(list);
+}
+
+/// `(remove list idx)` gives the removed item
+pub fn remove(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut list = args[0].clone();
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>{
+            let idx = list_index(&args[1], items.len(), false)?;
+            return Ok(items.remove(idx));
+        },
+        _=>bail!("Type error: `remove` only accepts Lists"),
+    }
+}
+
+/// `(set list idx item)` replaces the item and returns the list
+pub fn set(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut list = args[0].clone();
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>{
+            let idx = list_index(&args[1], items.len(), false)?;
+            items[idx] = args[2].clone();
+        },
+        _=>bail!("Type error: `set` only accepts Lists"),
+    }
+    drop(list_ref);
+
+    return Ok(list);
+}
+
+/// `(extend list others...)` adds the items of the other lists to the end and returns the list
+pub fn extend(mut args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.is_empty() {
+        bail!("`extend` needs a list");
+    }
+
+    let mut list = args.remove(0);
+    let mut new_items = Vec::new();
+    for arg in args.iter() {
+        new_items.extend(get_items(arg, "extend")?);
+    }
+
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>items.extend(new_items),
+        _=>bail!("Type error: `extend` only accepts Lists"),
+    }
+    drop(list_ref);
+
+    return Ok(list);
+}
+
+pub fn clear(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut list = args[0].clone();
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>items.clear(),
+        _=>bail!("Type error: `clear` only accepts Lists"),
+    }
+    drop(list_ref);
+
+    return Ok(list);
+}
+
+pub fn reverse_in_place(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut list = args[0].clone();
+    let mut list_ref = list.try_get_data_mut()?;
+    match &mut *list_ref {
+        Data::List(items)=>items.reverse(),
+        _=>bail!("Type error: `reverseInPlace` only accepts Lists"),
+    }
+    drop(list_ref);
+
+    return Ok(list);
+}
+
+/// `(get list idx)`. Unlike `index`, negative indices count from the end.
+pub fn get(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    match &*args[0].get_data() {
+        Data::List(items)=>{
+            let idx = list_index(&args[1], items.len(), false)?;
+            return Ok(items[idx].clone());
+        },
+        _=>bail!("Type error: `get` only accepts Lists"),
+    }
+}
+
+/// Makes a new list with the items of all the lists
+pub fn concat(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut out = Vec::new();
+    for arg in args.iter() {
+        out.extend(get_items(arg, "concat")?);
+    }
+
+    return Ok(i.alloc(Data::List(out)));
+}
+
+/// `(slice list start)` or `(slice list start end)`. The end is not included, and both can be
+/// negative to count from the end.
+pub fn slice(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 2 && args.len() != 3 {
+        bail!("`slice` takes a list, a start, and an optional end");
+    }
+
+    let items = get_items(&args[0], "slice")?;
+    let start = list_index(&args[1], items.len(), true)?;
+    let end = match args.get(2) {
+        Some(end)=>list_index(end, items.len(), true)?,
+        None=>items.len(),
+    };
+    if start > end {
+        bail!("Slice start is after the end");
+    }
+
+    return Ok(i.alloc(Data::List(items[start..end].to_vec())));
+}
+
+/// Makes a new list with the items in reverse
+pub fn reverse(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut items = get_items(&args[0], "reverse")?;
+    items.reverse();
+
+    return Ok(i.alloc(Data::List(items)));
+}
+
+/// Uses `=`, so objects with `$eq` are compared with it
+pub fn contains(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let items = get_items(&args[0], "contains")?;
+    for item in items.iter() {
+        if data_eq(item, &args[1], i, state)? {
+            return Ok(i.alloc(Data::Bool(true)));
+        }
+    }
+
+    return Ok(i.alloc(Data::Bool(false)));
+}
+
+/// Gives the index of the first equal item, or `None`
+pub fn index_of(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let items = get_items(&args[0], "indexOf")?;
+    for (idx, item) in items.iter().enumerate() {
+        if data_eq(item, &args[1], i, state)? {
+            return Ok(i.alloc(Data::Number(idx as i64)));
+        }
+    }
+
+    return Ok(i.alloc(Data::None));
+}
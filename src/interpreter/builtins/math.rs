@@ -0,0 +1,295 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use num_bigint::BigInt;
+use num_traits::{
+    FromPrimitive,
+    Signed,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeFn,
+    ArgCount,
+    arithmetic::{
+        compare_less,
+        as_f64,
+        as_bigint,
+    },
+};
+
+
+/// Functions that take a Number or Float and always give a Float
+macro_rules! float_fn {
+    ($name: ident)=>{
+        pub fn $name(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+            let f = get_float(&args[0], stringify!($name))?;
+            return Ok(i.alloc(Data::Float(f.$name())));
+        }
+    };
+}
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(sqrt, 1),
+    builtin!(cbrt, 1),
+    builtin!(pow, 2),
+    builtin!(exp, 1),
+    builtin!(ln, 1),
+    builtin!(log, Any),
+    builtin!(log2, 1),
+    builtin!(log10, 1),
+    builtin!(hypot, 2),
+
+    builtin!(sin, 1),
+    builtin!(cos, 1),
+    builtin!(tan, 1),
+    builtin!(asin, 1),
+    builtin!(acos, 1),
+    builtin!(atan, 1),
+    builtin!(atan2, 2),
+
+    builtin!(abs, 1),
+    builtin!(sign, 1),
+    builtin!(floor, 1),
+    builtin!(ceil, 1),
+    builtin!(round, 1),
+    builtin!(trunc, 1),
+    builtin!(min, Any),
+    builtin!(max, Any),
+    builtin!(clamp, 3),
+    builtin!(is_nan, isNan, 1),
+    builtin!(is_finite, isFinite, 1),
+
+    builtin!(to_float, toFloat, 1),
+    builtin!(to_int, toInt, 1),
+    builtin!(to_byte, toByte, 1),
+];
+
+/// Pinned values put in `std/math` next to the functions
+pub const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("tau", std::f64::consts::TAU),
+    ("e", std::f64::consts::E),
+    ("inf", f64::INFINITY),
+    ("nan", f64::NAN),
+];
+
+
+/// Numbers are promoted to Floats
+fn get_float(dr: &DataRef, name: &str)->Result<f64> {
+    match as_f64(&dr.get_data()) {
+        Some(f)=>Ok(f),
+        None=>bail!("Type error: `{name}` only accepts Numbers and Floats"),
+    }
+}
+
+/// Used when a Float has to become a Number. Big Floats become a BigInt instead of saturating
+/// like `as` does.
+fn float_to_int(f: f64, name: &str)->Result<Data> {
+    match BigInt::from_f64(f) {
+        Some(n)=>Ok(Data::from_bigint(n)),
+        None=>bail!("`{name}` cannot convert NaN or infinity to a Number"),
+    }
+}
+
+float_fn!(sqrt);
+float_fn!(cbrt);
+float_fn!(exp);
+float_fn!(ln);
+float_fn!(log2);
+float_fn!(log10);
+float_fn!(sin);
+float_fn!(cos);
+float_fn!(tan);
+float_fn!(asin);
+float_fn!(acos);
+float_fn!(atan);
+
+/// `(pow base exp)`. Stays an integer if both are integers and `exp` is not negative.
+pub fn pow(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let base = as_bigint(&args[0].get_data());
+    if let (Some(base), Data::Number(exp)) = (base, &*args[1].get_data()) {
+        if *exp >= 0 {
+            let Ok(exp) = u32::try_from(*exp) else {
+                bail!("`pow` got an exponent that is too big");
+            };
+            return Ok(i.alloc(Data::from_bigint(base.pow(exp))));
+        }
+    }
+
+    let base = get_float(&args[0], "pow")?;
+    let exp = get_float(&args[1], "pow")?;
+
+    return Ok(i.alloc(Data::Float(base.powf(exp))));
+}
+
+/// `(log x)` is the natural log, and `(log x base)` uses the base
+pub fn log(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let f = match args.as_slice() {
+        [x]=>get_float(x, "log")?.ln(),
+        [x, base]=>get_float(x, "log")?.log(get_float(base, "log")?),
+        _=>bail!("`log` takes a number and an optional base"),
+    };
+
+    return Ok(i.alloc(Data::Float(f)));
+}
+
+pub fn hypot(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let x = get_float(&args[0], "hypot")?;
+    let y = get_float(&args[1], "hypot")?;
+
+    return Ok(i.alloc(Data::Float(x.hypot(y))));
+}
+
+/// `(atan2 y x)`
+pub fn atan2(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let y = get_float(&args[0], "atan2")?;
+    let x = get_float(&args[1], "atan2")?;
+
+    return Ok(i.alloc(Data::Float(y.atan2(x))));
+}
+
+pub fn abs(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data = match &*args[0].get_data() {
+        Data::Number(n)=>match n.checked_abs() {
+            Some(n)=>Data::Number(n),
+            None=>Data::from_bigint(BigInt::from(*n).abs()),
+        },
+        Data::BigInt(n)=>Data::from_bigint(n.abs()),
+        Data::Float(f)=>Data::Float(f.abs()),
+        _=>bail!("Type error: `abs` only accepts Numbers and Floats"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+/// -1, 0, or 1 for Numbers, and -1.0, 1.0, or NaN for Floats
+pub fn sign(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data = match &*args[0].get_data() {
+        Data::Number(n)=>Data::Number(n.signum()),
+        Data::BigInt(n)=>Data::Number(if n.is_negative() {-1} else {1}),
+        Data::Float(f)=>Data::Float(f.signum()),
+        _=>bail!("Type error: `sign` only accepts Numbers and Floats"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+/// Shared by the rounding functions, which all give a Number
+fn round_with(args: &[DataRef], name: &str, f: fn(f64)->f64, i: &mut Interpreter)->Result<DataRef> {
+    let data = match &*args[0].get_data() {
+        Data::Float(x)=>float_to_int(f(*x), name)?,
+        n@(Data::Number(_)|Data::BigInt(_))=>n.clone(),
+        _=>bail!("Type error: `{name}` only accepts Numbers and Floats"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+pub fn floor(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return round_with(&args, "floor", f64::floor, i);
+}
+
+pub fn ceil(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return round_with(&args, "ceil", f64::ceil, i);
+}
+
+/// Rounds half away from zero
+pub fn round(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return round_with(&args, "round", f64::round, i);
+}
+
+pub fn trunc(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return round_with(&args, "trunc", f64::trunc, i);
+}
+
+/// Uses the same ordering as `<`, so it works on anything that can be compared
+fn pick(args: Vec<DataRef>, name: &str, want_less: bool, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let mut iter = args.into_iter();
+    let Some(mut best) = iter.next() else {
+        bail!("`{name}` needs at least one argument");
+    };
+
+    for arg in iter {
+        let better = if want_less {
+            compare_less(&arg, &best, i, state)?
+        } else {
+            compare_less(&best, &arg, i, state)?
+        };
+        if better {
+            best = arg;
+        }
+    }
+
+    return Ok(best);
+}
+
+pub fn min(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    return pick(args, "min", true, i, state);
+}
+
+pub fn max(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    return pick(args, "max", false, i, state);
+}
+
+/// `(clamp x low high)`
+pub fn clamp(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if compare_less(&args[2], &args[1], i, state)? {
+        bail!("`clamp` got a `high` that is less than `low`");
+    }
+
+    if compare_less(&args[0], &args[1], i, state)? {
+        return Ok(args[1].clone());
+    }
+    if compare_less(&args[2], &args[0], i, state)? {
+        return Ok(args[2].clone());
+    }
+
+    return Ok(args[0].clone());
+}
+
+pub fn is_nan(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let nan = get_float(&args[0], "isNan")?.is_nan();
+    return Ok(i.alloc(Data::Bool(nan)));
+}
+
+pub fn is_finite(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let finite = get_float(&args[0], "isFinite")?.is_finite();
+    return Ok(i.alloc(Data::Bool(finite)));
+}
+
+pub fn to_float(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let f = get_float(&args[0], "toFloat")?;
+    return Ok(i.alloc(Data::Float(f)));
+}
+
+/// Floats are truncated towards zero, Chars give their code point, and Bytes are unsigned
+pub fn to_int(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data = match &*args[0].get_data() {
+        Data::Float(f)=>float_to_int(*f, "toInt")?,
+        Data::Char(c)=>Data::Number(*c as i64),
+        Data::Byte(b)=>Data::Number(*b as i64),
+        n@(Data::Number(_)|Data::BigInt(_))=>n.clone(),
+        _=>bail!("Type error: `toInt` only accepts Numbers, Floats, Chars, and Bytes"),
+    };
+
+    return Ok(i.alloc(data));
+}
+
+/// Errors if the Number is not between 0 and 255
+pub fn to_byte(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let b = match &*args[0].get_data() {
+        Data::Byte(b)=>*b,
+        Data::Number(n)=>match u8::try_from(*n) {
+            Ok(b)=>b,
+            Err(_)=>bail!("`toByte` got {n}, which is not between 0 and 255"),
+        },
+        _=>bail!("Type error: `toByte` only accepts Numbers and Bytes"),
+    };
+
+    return Ok(i.alloc(Data::Byte(b)));
+}
@@ -4,7 +4,7 @@ use anyhow::{
 };
 use super::{
     Interpreter,
-    Interner,
+    ConvertState,
     Data,
     DataRef,
     NativeFn,
@@ -18,7 +18,7 @@ pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
 ];
 
 
-pub fn split_list(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn split_list(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     if args.len() != 2 {
         bail!("`split` can only take two arguments");
     }
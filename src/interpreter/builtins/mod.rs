@@ -1,10 +1,19 @@
 use super::{
     Interpreter,
-    Interner,
+    ConvertState,
+    Ident,
     NativeFn,
     NativeData,
+    NativeIter,
+    LazySeq,
+    LazySource,
     Data,
     DataRef,
+    ExternalData,
+    DataSet,
+    DataPMap,
+    ObjectMap,
+    SetKey,
     ArgCount,
 };
 
@@ -31,3 +40,16 @@ pub mod core;
 pub mod string;
 pub mod misc;
 pub mod io;
+pub mod set;
+pub mod persistent;
+pub mod seq;
+pub mod iter;
+pub mod lazy;
+pub mod list;
+pub mod object;
+pub mod regex;
+pub mod math;
+pub mod array;
+pub mod random;
+pub mod time;
+pub mod fs;
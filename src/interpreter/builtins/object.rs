@@ -0,0 +1,142 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Ident,
+    Data,
+    DataRef,
+    ObjectMap,
+    NativeFn,
+    ArgCount,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(has, 2),
+    builtin!(remove, 2),
+    builtin!(merge, Any),
+    builtin!(keys, 1),
+    builtin!(values, 1),
+    builtin!(entries, 1),
+    builtin!(from_entries, fromEntries, 1),
+];
+
+
+/// Field names can be given as `.name` or as a String
+fn field_name(dr: &DataRef, state: &mut ConvertState)->Result<Ident> {
+    match &*dr.get_data() {
+        Data::Ident(i)=>Ok(*i),
+        Data::String(s)=>Ok(state.intern(s.as_str())),
+        _=>bail!("Type error: Field names must be an Ident or a String"),
+    }
+}
+
+fn get_fields(dr: &DataRef, name: &str)->Result<ObjectMap> {
+    match &*dr.get_data() {
+        Data::Object(fields)=>Ok(fields.clone()),
+        _=>bail!("Type error: `{name}` only accepts Objects"),
+    }
+}
+
+/// `(has obj .field)`
+pub fn has(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let name = field_name(&args[1], state)?;
+
+    match &*args[0].get_data() {
+        Data::Object(fields)=>Ok(i.alloc(Data::Bool(fields.contains_key(&name)))),
+        _=>bail!("Type error: `has` only accepts Objects"),
+    }
+}
+
+/// `(remove obj .field)` gives the removed value, or `None` if there was no field
+pub fn remove(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let name = field_name(&args[1], state)?;
+
+    let mut obj = args[0].clone();
+    let mut obj_ref = obj.try_get_data_mut()?;
+    match &mut *obj_ref {
+        Data::Object(fields)=>{
+            i.check_remove_field(fields, name, &state.interner)?;
+
+            // `shift_remove` keeps the order of the other fields
+            let removed = fields.shift_remove(&name);
+            drop(obj_ref);
+
+            return Ok(removed.unwrap_or_else(||i.alloc(Data::None)));
+        },
+        _=>bail!("Type error: `remove` only accepts Objects"),
+    }
+}
+
+/// Makes a new object with the fields of all the objects. Later objects win. The new object is a
+/// plain object, the same as splatting into `object`.
+pub fn merge(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut out = ObjectMap::default();
+    for arg in args.iter() {
+        out.extend(get_fields(arg, "merge")?
+            .into_iter()
+            .filter(|(name, _)|!i.is_marker_field(*name))
+        );
+    }
+
+    return Ok(i.alloc(Data::Object(out)));
+}
+
+pub fn keys(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let fields = get_fields(&args[0], "keys")?;
+    let list = fields.into_keys()
+        .map(|name|i.alloc(Data::Ident(name)))
+        .collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+pub fn values(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let fields = get_fields(&args[0], "values")?;
+    let list = fields.into_values().collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+/// Gives a list of `(name value)` lists, the same as `core/fields`
+pub fn entries(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let fields = get_fields(&args[0], "entries")?;
+    let list = fields.into_iter()
+        .map(|(name, value)|{
+            let name = i.alloc(Data::Ident(name));
+            i.alloc(Data::List(vec![name, value]))
+        })
+        .collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+/// The opposite of `entries`. `$type` and `$consts` are skipped, so it can't make a struct or
+/// module.
+pub fn from_entries(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let entries = match &*args[0].get_data() {
+        Data::List(items)=>items.clone(),
+        _=>bail!("Type error: `fromEntries` only accepts a List of `(name value)` lists"),
+    };
+
+    let mut out = ObjectMap::default();
+    for entry in entries {
+        let entry_ref = entry.get_data();
+        let Data::List(pair) = &*entry_ref else {
+            bail!("Type error: `fromEntries` only accepts a List of `(name value)` lists");
+        };
+        if pair.len() != 2 {
+            bail!("`fromEntries` only accepts a List of `(name value)` lists");
+        }
+
+        let name = field_name(&pair[0], state)?;
+        if !i.is_marker_field(name) {
+            out.insert(name, pair[1].clone());
+        }
+    }
+
+    return Ok(i.alloc(Data::Object(out)));
+}
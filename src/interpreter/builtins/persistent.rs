@@ -0,0 +1,249 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    DataPMap,
+    NativeFn,
+    ArgCount,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(vector, Any),
+    builtin!(hash_map, hashMap, Any),
+    builtin!(conj, Any),
+    builtin!(assoc, Any),
+    builtin!(dissoc, Any),
+    builtin!(get, Any),
+    builtin!(contains, 2),
+    builtin!(to_list, toList, 1),
+];
+
+
+fn vector_index(data: &Data, len: usize)->Result<usize> {
+    match data {
+        Data::Number(n)=>{
+            if *n < 0 || *n > len as i64 {
+                bail!("Index out of bounds");
+            }
+
+            Ok(*n as usize)
+        },
+        _=>bail!("Type error: Vectors can only be indexed with a Number"),
+    }
+}
+
+fn map_insert(map: &DataPMap, key: DataRef, value: DataRef, i: &mut Interpreter, state: &mut ConvertState)->Result<DataPMap> {
+    let hash_key = i.set_key(&key, state)?;
+
+    Ok(map.insert(hash_key, (key, value)))
+}
+
+pub fn vector(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    Ok(i.alloc(Data::PVec(args.into_iter().collect())))
+}
+
+pub fn hash_map(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.len() % 2 != 0 {
+        bail!("`hashMap` takes key value pairs, but got an odd number of arguments");
+    }
+
+    let mut map = DataPMap::new();
+    let mut iter = args.into_iter();
+    while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+        map = map_insert(&map, key, value, i, state)?;
+    }
+
+    return Ok(i.alloc(Data::PMap(map)));
+}
+
+/// Add items to a collection. For maps each item is a `(key value)` list.
+pub fn conj(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.is_empty() {
+        bail!("`conj` needs a collection");
+    }
+
+    let mut iter = args.into_iter();
+    let coll = iter.next().unwrap();
+    let coll_ref = coll.get_data();
+
+    let new_data = match &*coll_ref {
+        Data::PVec(items)=>{
+            let mut items = items.clone();
+            for item in iter {
+                items = items.push(item);
+            }
+
+            Data::PVec(items)
+        },
+        Data::PMap(map)=>{
+            // keys can have a `$hash` method, so don't hold any borrows while hashing them
+            let mut map = map.clone();
+            drop(coll_ref);
+
+            for item in iter {
+                let item_ref = item.get_data();
+                let Data::List(pair) = &*item_ref else {
+                    bail!("Type error: `conj` on a map only accepts `(key value)` lists");
+                };
+                if pair.len() != 2 {
+                    bail!("`conj` on a map only accepts `(key value)` lists");
+                }
+                let (key, value) = (pair[0].clone(), pair[1].clone());
+                drop(item_ref);
+
+                map = map_insert(&map, key, value, i, state)?;
+            }
+
+            return Ok(i.alloc(Data::PMap(map)));
+        },
+        _=>bail!("Type error: `conj` only accepts persistent vectors and maps"),
+    };
+    drop(coll_ref);
+
+    return Ok(i.alloc(new_data));
+}
+
+pub fn assoc(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.len() % 2 != 1 {
+        bail!("`assoc` takes a collection and key value pairs");
+    }
+
+    let mut iter = args.into_iter();
+    let coll = iter.next().unwrap();
+    let coll_ref = coll.get_data();
+
+    let new_data = match &*coll_ref {
+        Data::PVec(items)=>{
+            let mut items = items.clone();
+            while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+                let idx = vector_index(&key.get_data(), items.len())?;
+                items = items.set(idx, value).unwrap();
+            }
+
+            Data::PVec(items)
+        },
+        Data::PMap(map)=>{
+            let mut map = map.clone();
+            drop(coll_ref);
+
+            while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+                map = map_insert(&map, key, value, i, state)?;
+            }
+
+            return Ok(i.alloc(Data::PMap(map)));
+        },
+        _=>bail!("Type error: `assoc` only accepts persistent vectors and maps"),
+    };
+    drop(coll_ref);
+
+    return Ok(i.alloc(new_data));
+}
+
+pub fn dissoc(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.is_empty() {
+        bail!("`dissoc` needs a map");
+    }
+
+    let mut iter = args.into_iter();
+    let coll = iter.next().unwrap();
+    let coll_ref = coll.get_data();
+
+    match &*coll_ref {
+        Data::PMap(map)=>{
+            let mut map = map.clone();
+            drop(coll_ref);
+
+            for key in iter {
+                let hash_key = i.set_key(&key, state)?;
+                map = map.remove(&hash_key);
+            }
+
+            return Ok(i.alloc(Data::PMap(map)));
+        },
+        _=>bail!("Type error: `dissoc` only accepts persistent maps"),
+    }
+}
+
+/// `(get coll key)` or `(get coll key default)`. Missing items give `None` or the default.
+pub fn get(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 2 && args.len() != 3 {
+        bail!("`get` takes a collection, a key, and an optional default");
+    }
+
+    let is_map = matches!(&*args[0].get_data(), Data::PMap(_));
+    let hash_key = if is_map {
+        Some(i.set_key(&args[1], state)?)
+    } else {
+        None
+    };
+
+    let found = match &*args[0].get_data() {
+        Data::PVec(items)=>match &*args[1].get_data() {
+            Data::Number(n)=>if *n < 0 {
+                None
+            } else {
+                items.get(*n as usize).cloned()
+            },
+            _=>bail!("Type error: Vectors can only be indexed with a Number"),
+        },
+        Data::PMap(map)=>map.get(hash_key.as_ref().unwrap()).map(|(_, value)|value.clone()),
+        _=>bail!("Type error: `get` only accepts persistent vectors and maps"),
+    };
+
+    match found {
+        Some(dr)=>Ok(dr),
+        None=>match args.get(2) {
+            Some(default)=>Ok(default.clone()),
+            None=>Ok(i.alloc(Data::None)),
+        },
+    }
+}
+
+pub fn contains(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let key = i.set_key(&args[1], state);
+
+    let contained = match &*args[0].get_data() {
+        Data::PVec(items)=>match &*args[1].get_data() {
+            Data::Number(n)=>*n >= 0 && (*n as usize) < items.len(),
+            _=>false,
+        },
+        Data::PMap(map)=>match key {
+            Ok(key)=>map.contains_key(&key),
+            Err(_)=>false,
+        },
+        _=>bail!("Type error: `contains` only accepts persistent vectors and maps"),
+    };
+
+    return Ok(i.alloc(Data::Bool(contained)));
+}
+
+/// Vectors become a list of their items, and maps become a list of `(key value)` lists
+pub fn to_list(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data_ref = args[0].get_data();
+    match &*data_ref {
+        Data::PVec(items)=>{
+            let list = items.to_vec();
+            drop(data_ref);
+
+            return Ok(i.alloc(Data::List(list)));
+        },
+        Data::PMap(map)=>{
+            let mut pairs = Vec::with_capacity(map.len());
+            map.for_each(|_, (key, value)|pairs.push((key.clone(), value.clone())));
+            drop(data_ref);
+
+            let list = pairs.into_iter()
+                .map(|(key, value)|i.alloc(Data::List(vec![key, value])))
+                .collect();
+
+            return Ok(i.alloc(Data::List(list)));
+        },
+        _=>bail!("Type error: `toList` only accepts persistent vectors and maps"),
+    }
+}
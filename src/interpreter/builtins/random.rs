@@ -0,0 +1,321 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{
+        BuildHasher,
+        Hasher,
+    },
+    rc::Rc,
+    cell::RefCell,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeData,
+    NativeFn,
+    ArgCount,
+    arithmetic::as_f64,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(new, Any),
+    builtin!(seed, 1),
+
+    builtin!(int, Any),
+    builtin!(float, Any),
+    builtin!(range, Any),
+    builtin!(choice, Any),
+    builtin!(shuffle, Any),
+    builtin!(sample, Any),
+];
+
+
+thread_local!(
+    /// Used by every function that isn't given a generator
+    static DEFAULT_RNG: Rc<RefCell<Rng>> = Rc::new(RefCell::new(Rng::from_entropy()));
+);
+
+
+/// xoshiro256**, seeded with SplitMix64. We implement it ourselves instead of using a crate so the
+/// output for a given seed never changes between versions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rng {
+    state: [u64; 4],
+}
+impl Rng {
+    pub fn new(seed: u64)->Self {
+        let mut sm = seed;
+        let mut next = ||{
+            sm = sm.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = sm;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+
+        Rng {
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    /// `RandomState` is seeded randomly by the standard library, so we borrow that
+    pub fn from_entropy()->Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self::new(seed)
+    }
+
+    pub fn next_u64(&mut self)->u64 {
+        let s = &mut self.state;
+        let out = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        return out;
+    }
+
+    /// Between 0 and 1, not including 1
+    pub fn next_f64(&mut self)->f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Between 0 and `n`, not including `n`. Rejects the values that would make the result biased.
+    pub fn below(&mut self, n: u64)->u64 {
+        let zone = u64::MAX - (u64::MAX % n);
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
+
+
+/// Every function takes an optional generator as the first argument, and uses the default one if
+/// it isn't given
+fn split_rng(mut args: Vec<DataRef>)->(Rc<RefCell<Rng>>, Vec<DataRef>) {
+    let rng = match args.first() {
+        Some(dr)=>match &*dr.get_data() {
+            Data::NativeData(NativeData::Random(rng))=>Some(rng.clone()),
+            _=>None,
+        },
+        None=>None,
+    };
+    let Some(rng) = rng else {
+        return (DEFAULT_RNG.with(|rng|rng.clone()), args);
+    };
+    args.remove(0);
+
+    return (rng, args);
+}
+
+fn get_int(dr: &DataRef, name: &str)->Result<i64> {
+    match &*dr.get_data() {
+        Data::Number(n)=>Ok(*n),
+        _=>bail!("Type error: `{name}` only accepts Numbers here"),
+    }
+}
+
+fn get_items(dr: &DataRef, name: &str)->Result<Vec<DataRef>> {
+    match &*dr.get_data() {
+        Data::List(items)=>Ok(items.clone()),
+        _=>bail!("Type error: `{name}` only accepts Lists"),
+    }
+}
+
+/// `(new)` makes a generator with a random seed, and `(new seed)` makes one that always gives the
+/// same values for the seed
+pub fn new(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let rng = match args.as_slice() {
+        []=>Rng::from_entropy(),
+        [seed]=>Rng::new(get_int(seed, "new")? as u64),
+        _=>bail!("`new` takes an optional seed"),
+    };
+
+    return Ok(i.alloc(Data::NativeData(NativeData::Random(Rc::new(RefCell::new(rng))))));
+}
+
+/// Reseeds the default generator
+pub fn seed(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let seed = get_int(&args[0], "seed")?;
+    DEFAULT_RNG.with(|rng|*rng.borrow_mut() = Rng::new(seed as u64));
+
+    return Ok(i.alloc(Data::None));
+}
+
+/// `(int)` gives any Number
+pub fn int(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let (rng, args) = split_rng(args);
+    if !args.is_empty() {
+        bail!("`int` takes an optional generator");
+    }
+
+    let n = rng.borrow_mut().next_u64() as i64;
+    return Ok(i.alloc(Data::Number(n)));
+}
+
+/// `(float)` gives a Float between 0 and 1, not including 1
+pub fn float(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let (rng, args) = split_rng(args);
+    if !args.is_empty() {
+        bail!("`float` takes an optional generator");
+    }
+
+    let f = rng.borrow_mut().next_f64();
+    return Ok(i.alloc(Data::Float(f)));
+}
+
+/// `(range low high)` does not include `high`. Gives a Number if both are Numbers, and a Float
+/// otherwise.
+pub fn range(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let (rng, args) = split_rng(args);
+    let [low, high] = args.as_slice() else {
+        bail!("`range` takes an optional generator, a low, and a high");
+    };
+
+    if let (Data::Number(low), Data::Number(high)) = (&*low.get_data(), &*high.get_data()) {
+        if low >= high {
+            bail!("`range` got a `high` that is not greater than `low`");
+        }
+        let span = high.wrapping_sub(*low) as u64;
+        let n = low.wrapping_add(rng.borrow_mut().below(span) as i64);
+
+        return Ok(i.alloc(Data::Number(n)));
+    }
+
+    let (Some(low), Some(high)) = (as_f64(&low.get_data()), as_f64(&high.get_data())) else {
+        bail!("Type error: `range` only accepts Numbers and Floats");
+    };
+    if low.is_nan() || high.is_nan() || low >= high {
+        bail!("`range` got a `high` that is not greater than `low`");
+    }
+    let f = low + (high - low) * rng.borrow_mut().next_f64();
+
+    return Ok(i.alloc(Data::Float(f)));
+}
+
+/// Gives a random item of the list, or `None` if it is empty
+pub fn choice(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let (rng, args) = split_rng(args);
+    let [list] = args.as_slice() else {
+        bail!("`choice` takes an optional generator and a list");
+    };
+
+    let items = get_items(list, "choice")?;
+    if items.is_empty() {
+        return Ok(i.alloc(Data::None));
+    }
+    let idx = rng.borrow_mut().below(items.len() as u64) as usize;
+
+    return Ok(items[idx].clone());
+}
+
+/// Shuffles the list in place and returns it
+pub fn shuffle(args: Vec<DataRef>, _: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let (rng, args) = split_rng(args);
+    let [list] = args.as_slice() else {
+        bail!("`shuffle` takes an optional generator and a list");
+    };
+
+    let mut list = list.clone();
+    let mut list_ref = list.try_get_data_mut()?;
+    let Data::List(items) = &mut *list_ref else {
+        bail!("Type error: `shuffle` only accepts Lists");
+    };
+    let mut rng = rng.borrow_mut();
+    for idx in (1..items.len()).rev() {
+        let other = rng.below(idx as u64 + 1) as usize;
+        items.swap(idx, other);
+    }
+    drop(list_ref);
+
+    return Ok(list);
+}
+
+/// `(sample list count)` gives a new list of `count` different items from the list
+pub fn sample(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let (rng, args) = split_rng(args);
+    let [list, count] = args.as_slice() else {
+        bail!("`sample` takes an optional generator, a list, and a count");
+    };
+
+    let mut items = get_items(list, "sample")?;
+    let count = get_int(count, "sample")?;
+    if count < 0 || count as usize > items.len() {
+        bail!("`sample` cannot take {count} items from a list of {}", items.len());
+    }
+    let count = count as usize;
+
+    // Partial Fisher-Yates. The first `count` items end up being the sample.
+    let mut rng = rng.borrow_mut();
+    for idx in 0..count {
+        let other = idx + rng.below((items.len() - idx) as u64) as usize;
+        items.swap(idx, other);
+    }
+    items.truncate(count);
+
+    return Ok(i.alloc(Data::List(items)));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeding_matches_splitmix64() {
+        // The first four SplitMix64 outputs for a seed of 0
+        let rng = Rng::new(0);
+        assert_eq!(rng.state, [
+            0xe220a8397b1dcdaf,
+            0x6e789e6aa1b965f4,
+            0x06c45d188009454f,
+            0xf88bb8a8724c81ec,
+        ]);
+    }
+
+    /// If any of these change, every seeded script gives different values than before
+    #[test]
+    fn fixed_seed_output() {
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.next_u64(), 0x15780b2e0c2ec716);
+        assert_eq!(rng.next_u64(), 0x6104d9866d113a7e);
+        assert_eq!(rng.next_u64(), 0xae17533239e499a1);
+        assert_eq!(rng.next_u64(), 0xecb8ad4703b360a1);
+
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.next_f64(), 0.08386297105988216);
+        assert_eq!(rng.next_f64(), 0.3789802506626686);
+
+        let mut rng = Rng::new(7);
+        let digits: Vec<u64> = (0..8).map(|_|rng.below(10)).collect();
+        assert_eq!(digits, [4, 4, 8, 4, 4, 1, 6, 6]);
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        let mut rng = Rng::new(1);
+        for n in [1, 2, 3, 10, u64::MAX] {
+            for _ in 0..100 {
+                assert!(rng.below(n) < n);
+            }
+        }
+
+        for _ in 0..100 {
+            let f = rng.next_f64();
+            assert!((0.0..1.0).contains(&f));
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use regex::{
+    Regex,
+    Captures,
+};
+use std::rc::Rc;
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeData,
+    ObjectMap,
+    NativeFn,
+    ArgCount,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(compile, 1),
+    builtin!(is_match, isMatch, 2),
+    builtin!(find, 2),
+    builtin!(find_all, findAll, 2),
+    builtin!(captures, 2),
+    builtin!(replace, 3),
+];
+
+
+/// Takes a compiled regex, or a String to compile on the spot
+fn get_regex(dr: &DataRef, name: &str)->Result<Rc<Regex>> {
+    match &*dr.get_data() {
+        Data::NativeData(NativeData::Regex(re))=>Ok(re.clone()),
+        Data::String(s)=>Ok(Rc::new(Regex::new(s)?)),
+        _=>bail!("Type error: `{name}` needs a regex or a String pattern"),
+    }
+}
+
+fn get_string(dr: &DataRef, name: &str)->Result<String> {
+    match &*dr.get_data() {
+        Data::String(s)=>Ok(s.clone()),
+        _=>bail!("Type error: `{name}` can only search Strings"),
+    }
+}
+
+/// The regex crate gives byte offsets, but strings are indexed by char everywhere else
+fn char_index(s: &str, byte_idx: usize)->usize {
+    s[..byte_idx].chars().count()
+}
+
+/// Makes `(object .text .start .end)` for the match
+fn match_object(s: &str, text: &str, start: usize, end: usize, i: &mut Interpreter, state: &mut ConvertState)->DataRef {
+    let mut fields = ObjectMap::default();
+    fields.insert(state.intern("text"), i.alloc(Data::String(text.to_string())));
+    fields.insert(state.intern("start"), i.alloc(Data::Number(char_index(s, start) as i64)));
+    fields.insert(state.intern("end"), i.alloc(Data::Number(char_index(s, end) as i64)));
+
+    return i.alloc(Data::Object(fields));
+}
+
+/// Same as `match_object` for the whole match, plus `.groups` with every group (the whole match is
+/// group 0) and `.named` with the named groups. Groups that didn't match are `None`.
+fn captures_object(re: &Regex, s: &str, caps: &Captures, i: &mut Interpreter, state: &mut ConvertState)->DataRef {
+    let whole = caps.get(0).unwrap();
+    let obj = match_object(s, whole.as_str(), whole.start(), whole.end(), i, state);
+
+    let groups = caps.iter()
+        .map(|group|match group {
+            Some(group)=>i.alloc(Data::String(group.as_str().to_string())),
+            None=>i.alloc(Data::None),
+        })
+        .collect();
+    let groups = i.alloc(Data::List(groups));
+
+    let mut named = ObjectMap::default();
+    for name in re.capture_names().flatten() {
+        let value = match caps.name(name) {
+            Some(group)=>i.alloc(Data::String(group.as_str().to_string())),
+            None=>i.alloc(Data::None),
+        };
+        named.insert(state.intern(name), value);
+    }
+    let named = i.alloc(Data::Object(named));
+
+    let mut obj_mut = obj.clone();
+    let mut obj_ref = obj_mut.get_data_mut();
+    let Data::Object(fields) = &mut *obj_ref else {unreachable!()};
+    fields.insert(state.intern("groups"), groups);
+    fields.insert(state.intern("named"), named);
+    drop(obj_ref);
+
+    return obj;
+}
+
+/// Compiles the pattern so it can be reused
+pub fn compile(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let re = get_regex(&args[0], "compile")?;
+
+    return Ok(i.alloc(Data::NativeData(NativeData::Regex(re))));
+}
+
+/// `(isMatch re s)`
+pub fn is_match(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let re = get_regex(&args[0], "isMatch")?;
+    let s = get_string(&args[1], "isMatch")?;
+
+    return Ok(i.alloc(Data::Bool(re.is_match(s.as_str()))));
+}
+
+/// `(find re s)` gives the first match, or `None`
+pub fn find(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let re = get_regex(&args[0], "find")?;
+    let s = get_string(&args[1], "find")?;
+
+    match re.find(s.as_str()) {
+        Some(m)=>Ok(match_object(&s, m.as_str(), m.start(), m.end(), i, state)),
+        None=>Ok(i.alloc(Data::None)),
+    }
+}
+
+/// `(findAll re s)` gives a list of all the matches
+pub fn find_all(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let re = get_regex(&args[0], "findAll")?;
+    let s = get_string(&args[1], "findAll")?;
+
+    let list = re.find_iter(s.as_str())
+        .map(|m|match_object(&s, m.as_str(), m.start(), m.end(), i, state))
+        .collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+/// `(captures re s)` gives the groups of the first match, or `None`
+pub fn captures(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let re = get_regex(&args[0], "captures")?;
+    let s = get_string(&args[1], "captures")?;
+
+    match re.captures(s.as_str()) {
+        Some(caps)=>Ok(captures_object(&re, &s, &caps, i, state)),
+        None=>Ok(i.alloc(Data::None)),
+    }
+}
+
+/// `(replace re s with)` replaces every match. `with` is either a String, where `$1` and `$name`
+/// are replaced with the groups, or a function that gets the captures and returns a String.
+pub fn replace(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let re = get_regex(&args[0], "replace")?;
+    let s = get_string(&args[1], "replace")?;
+
+    if let Data::String(with) = &*args[2].get_data() {
+        let out = re.replace_all(s.as_str(), with.as_str()).into_owned();
+        return Ok(i.alloc(Data::String(out)));
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut last_end = 0;
+    for caps in re.captures_iter(s.as_str()) {
+        let whole = caps.get(0).unwrap();
+        out.push_str(&s[last_end..whole.start()]);
+        last_end = whole.end();
+
+        let caps_obj = captures_object(&re, &s, &caps, i, state);
+        let res = i.call_value(args[2].clone(), vec![caps_obj], state)?;
+        match &*res.get_data() {
+            Data::String(with)=>out.push_str(with.as_str()),
+            _=>bail!("Type error: The `replace` function must return a String"),
+        }
+    }
+    out.push_str(&s[last_end..]);
+
+    return Ok(i.alloc(Data::String(out)));
+}
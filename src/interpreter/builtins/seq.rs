@@ -0,0 +1,370 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    ExternalData,
+    NativeIter,
+    NativeFn,
+    ArgCount,
+    arithmetic::compare_less,
+    lazy::seq_of,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(map, 2),
+    builtin!(filter, 2),
+    builtin!(reduce, Any),
+    builtin!(any, 2),
+    builtin!(all, 2),
+    builtin!(find, 2),
+    builtin!(sort, Any),
+    builtin!(sort_by, sortBy, Any),
+    builtin!(zip, Any),
+    builtin!(enumerate, 1),
+    builtin!(flatten, 1),
+    builtin!(partial, Any),
+    builtin!(comp, Any),
+];
+
+
+/// Gets the items of a list, string, set, or vector. Anything else goes through the iterator
+/// protocol. This realizes everything, so it is only used by functions that need every item anyway.
+///
+/// The callbacks we give the items to can collect, so all of them are rooted.
+fn get_items(coll: &DataRef, i: &mut Interpreter, state: &mut ConvertState)->Result<Vec<ExternalData>> {
+    let coll_ref = coll.get_data();
+    let items = match &*coll_ref {
+        Data::List(items)=>items.clone(),
+        Data::PVec(items)=>items.to_vec(),
+        Data::Set(items)=>items.values().cloned().collect(),
+        Data::String(s)=>{
+            let s = s.clone();
+            drop(coll_ref);
+
+            return Ok(s.chars()
+                .map(|c|i.alloc(Data::Char(c)).external())
+                .collect());
+        },
+        _=>{
+            drop(coll_ref);
+
+            let it = i.make_iter(coll, state)?.external();
+
+            let mut items = Vec::new();
+            while let Some(item) = i.iter_next(&it, state)? {
+                items.push(item.external());
+            }
+
+            return Ok(items);
+        },
+    };
+    drop(coll_ref);
+
+    return Ok(items.into_iter()
+        .map(DataRef::external)
+        .collect());
+}
+
+/// Lazy sequences and iterators might never end, so `map` and `filter` give a lazy sequence for
+/// them instead of a list
+fn is_lazy(coll: &DataRef, i: &Interpreter)->Result<bool> {
+    if matches!(&*coll.get_data(), Data::LazySeq(_)|Data::Iterator(_)) {
+        return Ok(true);
+    }
+
+    return Ok(i.get_method(coll, i.protocol.next)?.is_some());
+}
+
+/// Gives the first item that `f` gives `true` for. Only as many items as needed are realized, so
+/// this works on infinite sequences.
+fn find_item(
+    coll: &DataRef,
+    i: &mut Interpreter,
+    state: &mut ConvertState,
+    mut f: impl FnMut(&DataRef, &mut Interpreter, &mut ConvertState)->Result<bool>,
+)->Result<Option<DataRef>> {
+    let it = i.make_iter(coll, state)?.external();
+
+    while let Some(item) = i.iter_next(&it, state)? {
+        let item = item.external();
+        if f(&item, i, state)? {
+            return Ok(Some(item.inner()));
+        }
+    }
+
+    return Ok(None);
+}
+
+/// Same rules as `cond`: everything except `false` is true
+fn truthy(dr: &DataRef)->bool {
+    !matches!(&*dr.get_data(), Data::Bool(false))
+}
+
+fn to_list(items: Vec<ExternalData>, i: &mut Interpreter)->DataRef {
+    let list = items.into_iter()
+        .map(ExternalData::inner)
+        .collect();
+
+    return i.alloc(Data::List(list));
+}
+
+/// A stable merge sort that can fail. We can't use `sort_by` because the comparison calls back into
+/// the script, and a user comparator might not be a total order.
+fn merge_sort<T>(mut items: Vec<T>, less: &mut impl FnMut(&T, &T)->Result<bool>)->Result<Vec<T>> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, less)?;
+    let right = merge_sort(right, less)?;
+
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // only take from the right when it is strictly less so equal items keep their order
+        if less(r, l)? {
+            out.push(right.next().unwrap());
+        } else {
+            out.push(left.next().unwrap());
+        }
+    }
+    out.extend(left);
+    out.extend(right);
+
+    return Ok(out);
+}
+
+/// Uses the comparator if there is one, otherwise the normal `<`
+fn is_less(l: &DataRef, r: &DataRef, cmp: Option<&DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<bool> {
+    match cmp {
+        Some(cmp)=>{
+            let res = i.call_value(cmp.clone(), vec![l.clone(), r.clone()], state)?;
+            return Ok(truthy(&res));
+        },
+        None=>compare_less(l, r, i, state),
+    }
+}
+
+/// `(map coll f)`. Lazy sequences and iterators give a lazy sequence, and everything else gives a
+/// list.
+pub fn map(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if is_lazy(&args[0], i)? {
+        let inner = i.make_iter(&args[0], state)?;
+        let it = i.alloc(Data::Iterator(NativeIter::Map {
+            inner,
+            func: args[1].clone(),
+        }));
+
+        return Ok(seq_of(it, i));
+    }
+
+    let items = get_items(&args[0], i, state)?;
+
+    let mut out = Vec::with_capacity(items.len());
+    for item in items {
+        let res = i.call_value(args[1].clone(), vec![item.inner()], state)?;
+        out.push(res.external());
+    }
+
+    return Ok(to_list(out, i));
+}
+
+/// `(filter coll pred)`. Lazy sequences and iterators give a lazy sequence, and everything else
+/// gives a list.
+pub fn filter(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if is_lazy(&args[0], i)? {
+        let inner = i.make_iter(&args[0], state)?;
+        let it = i.alloc(Data::Iterator(NativeIter::Filter {
+            inner,
+            pred: args[1].clone(),
+        }));
+
+        return Ok(seq_of(it, i));
+    }
+
+    let items = get_items(&args[0], i, state)?;
+
+    let mut out = Vec::new();
+    for item in items {
+        let res = i.call_value(args[1].clone(), vec![(*item).clone()], state)?;
+        if truthy(&res) {
+            out.push(item);
+        }
+    }
+
+    return Ok(to_list(out, i));
+}
+
+/// `(reduce coll f)` or `(reduce coll f init)`. Without a starting value the first item is used,
+/// and an empty collection gives `None`.
+pub fn reduce(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 2 && args.len() != 3 {
+        bail!("`reduce` takes a collection, a function, and an optional starting value");
+    }
+
+    let mut items = get_items(&args[0], i, state)?.into_iter();
+    let mut acc = match args.get(2) {
+        Some(init)=>init.clone(),
+        None=>match items.next() {
+            Some(first)=>first.inner(),
+            None=>return Ok(i.alloc(Data::None)),
+        },
+    };
+
+    for item in items {
+        // the accumulator is only held by us while the function runs
+        let acc_root = acc.external();
+        acc = i.call_value(args[1].clone(), vec![(*acc_root).clone(), item.inner()], state)?;
+    }
+
+    return Ok(acc);
+}
+
+/// `(any coll pred)`
+pub fn any(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let found = find_item(&args[0], i, state, |item, i, state|{
+        let res = i.call_value(args[1].clone(), vec![item.clone()], state)?;
+        return Ok(truthy(&res));
+    })?;
+
+    return Ok(i.alloc(Data::Bool(found.is_some())));
+}
+
+/// `(all coll pred)`
+pub fn all(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let found = find_item(&args[0], i, state, |item, i, state|{
+        let res = i.call_value(args[1].clone(), vec![item.clone()], state)?;
+        return Ok(!truthy(&res));
+    })?;
+
+    return Ok(i.alloc(Data::Bool(found.is_none())));
+}
+
+/// `(find coll pred)` gives the first item matching, or `None`
+pub fn find(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let found = find_item(&args[0], i, state, |item, i, state|{
+        let res = i.call_value(args[1].clone(), vec![item.clone()], state)?;
+        return Ok(truthy(&res));
+    })?;
+
+    return Ok(found.unwrap_or_else(||i.alloc(Data::None)));
+}
+
+/// `(sort coll)` or `(sort coll less)`. Returns a new sorted list. The sort is stable.
+pub fn sort(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 1 && args.len() != 2 {
+        bail!("`sort` takes a collection and an optional comparator");
+    }
+
+    let items = get_items(&args[0], i, state)?;
+    let cmp = args.get(1);
+
+    let sorted = merge_sort(items, &mut |l, r|is_less(l, r, cmp, i, state))?;
+
+    return Ok(to_list(sorted, i));
+}
+
+/// `(sortBy coll key)` or `(sortBy coll key less)`. The key function is called once per item.
+pub fn sort_by(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 2 && args.len() != 3 {
+        bail!("`sortBy` takes a collection, a key function, and an optional comparator");
+    }
+
+    let items = get_items(&args[0], i, state)?;
+    let cmp = args.get(2);
+
+    let mut keyed = Vec::with_capacity(items.len());
+    for item in items {
+        let key = i.call_value(args[1].clone(), vec![(*item).clone()], state)?;
+        keyed.push((key.external(), item));
+    }
+
+    let sorted = merge_sort(keyed, &mut |(l, _), (r, _)|is_less(l, r, cmp, i, state))?;
+    let sorted = sorted.into_iter()
+        .map(|(_, item)|item)
+        .collect::<Vec<_>>();
+
+    return Ok(to_list(sorted, i));
+}
+
+/// `(zip coll...)` gives a list of lists. It stops at the end of the shortest collection, so only
+/// one of them has to be finite.
+pub fn zip(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let mut its = Vec::with_capacity(args.len());
+    for arg in args.iter() {
+        its.push(i.make_iter(arg, state)?.external());
+    }
+
+    let mut out = Vec::new();
+    'groups: while !its.is_empty() {
+        let mut group = Vec::with_capacity(its.len());
+        for it in its.iter() {
+            match i.iter_next(it, state)? {
+                Some(item)=>group.push(item.external()),
+                None=>break 'groups,
+            }
+        }
+        out.push(to_list(group, i).external());
+    }
+
+    return Ok(to_list(out, i));
+}
+
+/// `(enumerate coll)` gives a list of `(index item)` lists
+pub fn enumerate(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let items = get_items(&args[0], i, state)?;
+
+    let out = items.into_iter()
+        .enumerate()
+        .map(|(idx, item)|{
+            let idx = i.alloc(Data::Number(idx as i64));
+            i.alloc(Data::List(vec![idx, item.inner()]))
+        })
+        .collect();
+
+    return Ok(i.alloc(Data::List(out)));
+}
+
+/// Flattens one level of lists and vectors. Other items are kept as they are.
+pub fn flatten(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let items = get_items(&args[0], i, state)?;
+
+    let mut out = Vec::new();
+    for item in items {
+        match &*item.get_data() {
+            Data::List(inner)=>out.extend(inner.iter().cloned()),
+            Data::PVec(inner)=>out.extend(inner.to_vec()),
+            _=>out.push((*item).clone()),
+        }
+    }
+
+    return Ok(i.alloc(Data::List(out)));
+}
+
+/// `(partial f args...)` gives a function that calls `f` with `args` before any others
+pub fn partial(mut args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.is_empty() {
+        bail!("`partial` needs a function");
+    }
+
+    let func = args.remove(0);
+
+    return Ok(i.alloc(Data::Partial{func, args}));
+}
+
+/// `(comp f g h)` gives a function that does `(f (g (h args...)))`
+pub fn comp(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.is_empty() {
+        bail!("`comp` needs at least one function");
+    }
+
+    return Ok(i.alloc(Data::Composed(args)));
+}
@@ -0,0 +1,181 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    DataSet,
+    NativeFn,
+    ArgCount,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(new, Any),
+    builtin!(from_list, fromList, 1),
+    builtin!(to_list, toList, 1),
+    builtin!(add, 2),
+    builtin!(remove, 2),
+    builtin!(contains, 2),
+    builtin!(union, Any),
+    builtin!(intersection, Any),
+    builtin!(difference, Any),
+];
+
+
+fn insert_all(set: &mut DataSet, items: impl IntoIterator<Item = DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<()> {
+    for item in items {
+        let key = i.set_key(&item, state)?;
+        set.entry(key).or_insert(item);
+    }
+
+    return Ok(());
+}
+
+/// Clones the entries of each argument, making sure they are all sets
+fn get_sets(args: &[DataRef], name: &str)->Result<Vec<DataSet>> {
+    if args.is_empty() {
+        bail!("`{name}` needs at least one set");
+    }
+
+    let mut sets = Vec::with_capacity(args.len());
+    for arg in args {
+        match &*arg.get_data() {
+            Data::Set(items)=>sets.push(items.clone()),
+            _=>bail!("Type error: `{name}` only accepts Sets"),
+        }
+    }
+
+    return Ok(sets);
+}
+
+pub fn new(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let mut set = DataSet::default();
+    insert_all(&mut set, args, i, state)?;
+
+    return Ok(i.alloc(Data::Set(set)));
+}
+
+pub fn from_list(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let data_ref = args[0].get_data();
+    match &*data_ref {
+        Data::List(items)=>{
+            let items = items.clone();
+            drop(data_ref);
+
+            let mut set = DataSet::default();
+            insert_all(&mut set, items, i, state)?;
+
+            return Ok(i.alloc(Data::Set(set)));
+        },
+        _=>bail!("Type error: `fromList` only accepts Lists"),
+    }
+}
+
+pub fn to_list(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let data_ref = args[0].get_data();
+    match &*data_ref {
+        Data::Set(items)=>{
+            let list = items.values().cloned().collect();
+
+            return Ok(i.alloc(Data::List(list)));
+        },
+        _=>bail!("Type error: `toList` only accepts Sets"),
+    }
+}
+
+/// Returns `true` if the item was not already in the set
+pub fn add(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let key = i.set_key(&args[1], state)?;
+
+    let mut set = args[0].clone();
+    let mut set_ref = set.try_get_data_mut()?;
+    match &mut *set_ref {
+        Data::Set(items)=>{
+            if items.contains_key(&key) {
+                drop(set_ref);
+                return Ok(i.alloc(Data::Bool(false)));
+            }
+
+            items.insert(key, args[1].clone());
+            drop(set_ref);
+
+            return Ok(i.alloc(Data::Bool(true)));
+        },
+        _=>bail!("Type error: `add` only accepts Sets"),
+    }
+}
+
+/// Returns `true` if the item was in the set
+pub fn remove(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let key = i.set_key(&args[1], state)?;
+
+    let mut set = args[0].clone();
+    let mut set_ref = set.try_get_data_mut()?;
+    match &mut *set_ref {
+        Data::Set(items)=>{
+            // `shift_remove` keeps the insertion order of the other items intact
+            let removed = items.shift_remove(&key).is_some();
+            drop(set_ref);
+
+            return Ok(i.alloc(Data::Bool(removed)));
+        },
+        _=>bail!("Type error: `remove` only accepts Sets"),
+    }
+}
+
+pub fn contains(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let key = i.set_key(&args[1], state);
+
+    let data_ref = args[0].get_data();
+    match &*data_ref {
+        Data::Set(items)=>{
+            // unhashable values can never be in a set, so they are simply not contained
+            let contained = match key {
+                Ok(key)=>items.contains_key(&key),
+                Err(_)=>false,
+            };
+
+            return Ok(i.alloc(Data::Bool(contained)));
+        },
+        _=>bail!("Type error: `contains` only accepts Sets"),
+    }
+}
+
+pub fn union(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut sets = get_sets(&args, "union")?.into_iter();
+    let mut out = sets.next().unwrap();
+
+    for set in sets {
+        for (key, item) in set {
+            out.entry(key).or_insert(item);
+        }
+    }
+
+    return Ok(i.alloc(Data::Set(out)));
+}
+
+pub fn intersection(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut sets = get_sets(&args, "intersection")?.into_iter();
+    let mut out = sets.next().unwrap();
+
+    for set in sets {
+        out.retain(|key, _|set.contains_key(key));
+    }
+
+    return Ok(i.alloc(Data::Set(out)));
+}
+
+pub fn difference(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let mut sets = get_sets(&args, "difference")?.into_iter();
+    let mut out = sets.next().unwrap();
+
+    for set in sets {
+        out.retain(|key, _|!set.contains_key(key));
+    }
+
+    return Ok(i.alloc(Data::Set(out)));
+}
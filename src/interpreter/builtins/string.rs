@@ -2,14 +2,19 @@ use anyhow::{
     Result,
     bail,
 };
+use num_bigint::BigInt;
 use std::fmt::Write;
 use super::{
     Interpreter,
-    Interner,
+    ConvertState,
     Data,
     DataRef,
     NativeFn,
     ArgCount,
+    arithmetic::{
+        as_f64,
+        as_bigint,
+    },
     // DEBUG,
 };
 
@@ -17,20 +22,116 @@ use super::{
 pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
     builtin!(format, Any),
     builtin!(debug_format, debugFormat, Any),
+    builtin!(fmt, Any),
     builtin!(split, 2),
     builtin!(chars, 1),
+    builtin!(substring, Any),
+    builtin!(find, 2),
+    builtin!(rfind, 2),
+    builtin!(replace, 3),
+    builtin!(trim, 1),
+    builtin!(trim_start, trimStart, 1),
+    builtin!(trim_end, trimEnd, 1),
+    builtin!(upper, 1),
+    builtin!(lower, 1),
+    builtin!(starts_with, startsWith, 2),
+    builtin!(ends_with, endsWith, 2),
+    builtin!(join, 2),
+    builtin!(repeat, 2),
+    builtin!(pad_left, padLeft, Any),
+    builtin!(pad_right, padRight, Any),
+    builtin!(lines, 1),
+    builtin!(parse_int, parseInt, Any),
+    builtin!(parse_float, parseFloat, 1),
+    builtin!(to_string, toString, 1),
 ];
 
 
-pub fn format(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+/// `repeat` and the pad functions won't make a String bigger than this many bytes. Anything bigger
+/// is almost certainly a mistake, and failing to allocate it would abort.
+const MAX_BUILT_LEN: usize = 1 << 30;
+
+
+pub fn format(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
     let mut fmt = String::new();
     for arg in args {
-        format_data(&mut fmt, &arg.get_data());
+        format_value(&mut fmt, &arg, i, state)?;
     }
 
     return Ok(i.alloc(Data::String(fmt.into())));
 }
 
+/// Same as `format_data`, but objects with a `$fmt` method are formatted with it, even inside
+/// collections. The collections are copied first, because the method could change them.
+pub fn format_value(fmt: &mut String, dr: &DataRef, i: &mut Interpreter, state: &mut ConvertState)->Result<()> {
+    let data = dr.get_data();
+    match &*data {
+        Data::Object(_)=>{
+            drop(data);
+            let Some(method) = i.get_method(dr, i.protocol.fmt)? else {
+                format_data(fmt, &dr.get_data());
+                return Ok(());
+            };
+
+            let res = i.call_value(method, vec![dr.clone()], state)?;
+            match &*res.get_data() {
+                Data::String(s)=>write!(fmt, "{s}").unwrap(),
+                _=>bail!("Type error: `$fmt` must return a String"),
+            };
+        },
+        Data::List(items)=>{
+            let items = items.clone();
+            drop(data);
+
+            write!(fmt, "(").unwrap();
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {write!(fmt, " ").unwrap()}
+                format_value(fmt, item, i, state)?;
+            }
+            write!(fmt, ")").unwrap();
+        },
+        Data::Set(items)=>{
+            let items = items.values().cloned().collect::<Vec<_>>();
+            drop(data);
+
+            write!(fmt, "#{{").unwrap();
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {write!(fmt, " ").unwrap()}
+                format_value(fmt, item, i, state)?;
+            }
+            write!(fmt, "}}").unwrap();
+        },
+        Data::PVec(items)=>{
+            let items = items.to_vec();
+            drop(data);
+
+            write!(fmt, "[").unwrap();
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {write!(fmt, " ").unwrap()}
+                format_value(fmt, item, i, state)?;
+            }
+            write!(fmt, "]").unwrap();
+        },
+        Data::PMap(map)=>{
+            let mut pairs = Vec::with_capacity(map.len());
+            map.for_each(|_, (key, value)|pairs.push((key.clone(), value.clone())));
+            drop(data);
+
+            write!(fmt, "{{").unwrap();
+            for (idx, (key, value)) in pairs.iter().enumerate() {
+                if idx > 0 {write!(fmt, ", ").unwrap()}
+                format_value(fmt, key, i, state)?;
+                write!(fmt, " ").unwrap();
+                format_value(fmt, value, i, state)?;
+            }
+            write!(fmt, "}}").unwrap();
+        },
+        _=>format_data(fmt, &data),
+    }
+
+    return Ok(());
+}
+
 pub fn format_data(fmt: &mut String, data: &Data) {
     match data {
         Data::Char(c)=>write!(fmt, "\\{c}").unwrap(),
@@ -42,22 +143,58 @@ pub fn format_data(fmt: &mut String, data: &Data) {
             }
             write!(fmt, ")").unwrap();
         },
+        Data::Set(items)=>{
+            write!(fmt, "#{{").unwrap();
+            for (i, data) in items.values().enumerate() {
+                if i > 0 {write!(fmt, " ").unwrap()}
+                format_data(fmt, &data.get_data());
+            }
+            write!(fmt, "}}").unwrap();
+        },
+        Data::PVec(items)=>{
+            write!(fmt, "[").unwrap();
+            let mut i = 0;
+            items.for_each(|data|{
+                if i > 0 {write!(fmt, " ").unwrap()}
+                format_data(fmt, &data.get_data());
+                i += 1;
+            });
+            write!(fmt, "]").unwrap();
+        },
+        Data::PMap(map)=>{
+            write!(fmt, "{{").unwrap();
+            let mut i = 0;
+            map.for_each(|_, (key, value)|{
+                if i > 0 {write!(fmt, ", ").unwrap()}
+                format_data(fmt, &key.get_data());
+                write!(fmt, " ").unwrap();
+                format_data(fmt, &value.get_data());
+                i += 1;
+            });
+            write!(fmt, "}}").unwrap();
+        },
 
         Data::String(s)=>write!(fmt, "{s}").unwrap(),
         Data::Number(n)=>write!(fmt, "{n}").unwrap(),
+        Data::BigInt(n)=>write!(fmt, "{n}").unwrap(),
         Data::Float(f)=>write!(fmt, "{f}").unwrap(),
         Data::Bool(b)=>write!(fmt, "{b}").unwrap(),
+        Data::Byte(b)=>write!(fmt, "#x{b:02x}").unwrap(),
+        Data::TypedArray(arr)=>write!(fmt, "{arr}").unwrap(),
 
-        Data::Fn(_)|Data::Closure{..}=>write!(fmt, "<fn>").unwrap(),
+        Data::Fn(_)|Data::Closure{..}|Data::Partial{..}|Data::Composed(_)=>write!(fmt, "<fn>").unwrap(),
         Data::NativeFn(name, _, _)=>write!(fmt, "<nativeFn: {name}>").unwrap(),
         Data::None=>write!(fmt, "None").unwrap(),
         Data::NativeData(_)=>write!(fmt, "<nativeData>").unwrap(),
+        Data::Iterator(_)=>write!(fmt, "<iterator>").unwrap(),
+        Data::LazySeq(_)=>write!(fmt, "<lazySeq>").unwrap(),
+        Data::Done=>write!(fmt, "<done>").unwrap(),
         Data::Object(_)=>write!(fmt, "<object>").unwrap(),
         Data::Ident(_)=>write!(fmt, "<ident>").unwrap(),
     }
 }
 
-pub fn debug_format(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn debug_format(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     let mut fmt = String::new();
     for arg in args {
         debug_format_data(&mut fmt, &arg.get_data());
@@ -82,22 +219,265 @@ pub fn debug_format_data(fmt: &mut String, data: &Data) {
             }
             write!(fmt, ")").unwrap();
         },
+        Data::Set(items)=>{
+            write!(fmt, "#{{").unwrap();
+            for (i, data) in items.values().enumerate() {
+                if i > 0 {write!(fmt, " ").unwrap()}
+                format_data(fmt, &data.get_data());
+            }
+            write!(fmt, "}}").unwrap();
+        },
+        Data::PVec(items)=>{
+            write!(fmt, "[").unwrap();
+            let mut i = 0;
+            items.for_each(|data|{
+                if i > 0 {write!(fmt, " ").unwrap()}
+                format_data(fmt, &data.get_data());
+                i += 1;
+            });
+            write!(fmt, "]").unwrap();
+        },
+        Data::PMap(map)=>{
+            write!(fmt, "{{").unwrap();
+            let mut i = 0;
+            map.for_each(|_, (key, value)|{
+                if i > 0 {write!(fmt, ", ").unwrap()}
+                format_data(fmt, &key.get_data());
+                write!(fmt, " ").unwrap();
+                format_data(fmt, &value.get_data());
+                i += 1;
+            });
+            write!(fmt, "}}").unwrap();
+        },
 
         Data::String(s)=>write!(fmt, "{s}").unwrap(),
         Data::Number(n)=>write!(fmt, "{n}").unwrap(),
+        Data::BigInt(n)=>write!(fmt, "{n}").unwrap(),
         Data::Float(f)=>write!(fmt, "{f}").unwrap(),
         Data::Bool(b)=>write!(fmt, "{b}").unwrap(),
+        Data::Byte(b)=>write!(fmt, "#x{b:02x}").unwrap(),
+        Data::TypedArray(arr)=>write!(fmt, "{arr}").unwrap(),
 
-        Data::Fn(_)|Data::Closure{..}=>write!(fmt, "<fn>").unwrap(),
+        Data::Fn(_)|Data::Closure{..}|Data::Partial{..}|Data::Composed(_)=>write!(fmt, "<fn>").unwrap(),
         Data::NativeFn(name, _, _)=>write!(fmt, "<nativeFn: {name}>").unwrap(),
         Data::None=>write!(fmt, "None").unwrap(),
         Data::NativeData(_)=>write!(fmt, "<nativeData>").unwrap(),
+        Data::Iterator(_)=>write!(fmt, "<iterator>").unwrap(),
+        Data::LazySeq(_)=>write!(fmt, "<lazySeq>").unwrap(),
+        Data::Done=>write!(fmt, "<done>").unwrap(),
         Data::Object(_)=>write!(fmt, "<object>").unwrap(),
         Data::Ident(_)=>write!(fmt, "<ident>").unwrap(),
     }
 }
 
-pub fn chars(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+/// The part after the `:` in a `fmt` placeholder: `[[fill]align][0][width][.precision][type]`
+struct FmtSpec {
+    fill: char,
+    align: Option<char>,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+fn parse_fmt_spec(spec: &str)->Result<FmtSpec> {
+    let chars = spec.chars().collect::<Vec<_>>();
+    let mut idx = 0;
+
+    let mut fill = ' ';
+    let mut align = None;
+    if chars.len() >= 2 && matches!(chars[1], '<'|'^'|'>') {
+        fill = chars[0];
+        align = Some(chars[1]);
+        idx = 2;
+    } else if chars.first().is_some_and(|c|matches!(c, '<'|'^'|'>')) {
+        align = Some(chars[0]);
+        idx = 1;
+    }
+
+    let zero = chars.get(idx) == Some(&'0');
+    if zero {idx += 1}
+
+    let read_number = |idx: &mut usize|->Option<usize> {
+        let start = *idx;
+        while chars.get(*idx).is_some_and(|c|c.is_ascii_digit()) {
+            *idx += 1;
+        }
+        chars[start..*idx].iter().collect::<String>().parse().ok()
+    };
+
+    let width = read_number(&mut idx);
+    let mut precision = None;
+    if chars.get(idx) == Some(&'.') {
+        idx += 1;
+        precision = read_number(&mut idx);
+        if precision.is_none() {
+            bail!("Format spec `{spec}` has a `.` without a precision");
+        }
+    }
+
+    let kind = match chars.get(idx) {
+        Some(c@('x'|'X'|'b'|'o'|'?'))=>{
+            idx += 1;
+            Some(*c)
+        },
+        _=>None,
+    };
+    if idx != chars.len() {
+        bail!("Invalid format spec `{spec}`");
+    }
+
+    return Ok(FmtSpec {
+        fill,
+        align,
+        zero,
+        width,
+        precision,
+        kind,
+    });
+}
+
+/// `{}` takes the next positional argument, `{0}` takes one by index, and `{name}` takes the field
+/// from the last argument, which has to be an object.
+fn fmt_arg(arg: &str, args: &[DataRef], next_pos: &mut usize, state: &mut ConvertState)->Result<DataRef> {
+    let idx = if arg.is_empty() {
+        *next_pos += 1;
+        Some(*next_pos - 1)
+    } else {
+        arg.parse::<usize>().ok()
+    };
+
+    if let Some(idx) = idx {
+        match args.get(idx) {
+            Some(dr)=>return Ok(dr.clone()),
+            None=>bail!("`fmt` does not have an argument {idx}"),
+        }
+    }
+
+    let Some(last) = args.last() else {
+        bail!("`fmt` needs an Object for the named placeholder `{arg}`");
+    };
+    let name = state.intern(arg);
+    match &*last.get_data() {
+        Data::Object(fields)=>match fields.get(&name) {
+            Some(dr)=>Ok(dr.clone()),
+            None=>bail!("`fmt` does not have an argument named `{arg}`"),
+        },
+        _=>bail!("Type error: `fmt` needs an Object as the last argument for the named placeholder `{arg}`"),
+    }
+}
+
+fn format_with_spec(fmt: &mut String, dr: &DataRef, spec: &FmtSpec, i: &mut Interpreter, state: &mut ConvertState)->Result<()> {
+    let is_number = as_f64(&dr.get_data()).is_some();
+
+    let mut s = String::new();
+    match spec.kind {
+        Some('?')=>debug_format_data(&mut s, &dr.get_data()),
+        Some(kind)=>{
+            let n = match &*dr.get_data() {
+                Data::Byte(b)=>BigInt::from(*b),
+                data=>match as_bigint(data) {
+                    Some(n)=>n,
+                    None=>bail!("Type error: `{{:{kind}}}` can only format Numbers and Bytes"),
+                },
+            };
+            match kind {
+                'x'=>write!(s, "{n:x}").unwrap(),
+                'X'=>write!(s, "{n:X}").unwrap(),
+                'b'=>write!(s, "{n:b}").unwrap(),
+                'o'=>write!(s, "{n:o}").unwrap(),
+                _=>unreachable!(),
+            }
+        },
+        None=>{
+            // the precision is the number of decimals for numbers and the max length for strings
+            let precise = match (&*dr.get_data(), spec.precision) {
+                (Data::Float(f), Some(p))=>Some(format!("{f:.p$}")),
+                (n@(Data::Number(_)|Data::BigInt(_)), Some(p))=>Some(format!("{:.p$}", as_f64(n).unwrap())),
+                (Data::String(st), Some(p))=>Some(st.chars().take(p).collect()),
+                _=>None,
+            };
+            match precise {
+                Some(precise)=>s = precise,
+                None=>format_value(&mut s, dr, i, state)?,
+            }
+        },
+    }
+
+    let len = s.chars().count();
+    let width = spec.width.unwrap_or(0);
+    if len >= width {
+        fmt.push_str(s.as_str());
+        return Ok(());
+    }
+    let pad = width - len;
+
+    // `{:05}` puts the zeros after the sign
+    if spec.zero && spec.align.is_none() && is_number {
+        let (sign, digits) = match s.strip_prefix('-') {
+            Some(digits)=>("-", digits),
+            None=>("", s.as_str()),
+        };
+        write!(fmt, "{sign}{}{digits}", "0".repeat(pad)).unwrap();
+        return Ok(());
+    }
+
+    let fill = |count: usize|spec.fill.to_string().repeat(count);
+    match spec.align.unwrap_or(if is_number {'>'} else {'<'}) {
+        '<'=>write!(fmt, "{s}{}", fill(pad)).unwrap(),
+        '>'=>write!(fmt, "{}{s}", fill(pad)).unwrap(),
+        _=>write!(fmt, "{}{s}{}", fill(pad / 2), fill(pad - pad / 2)).unwrap(),
+    }
+
+    return Ok(());
+}
+
+/// `(fmt "{name:>10} {price:.2}" obj)`. Placeholders are `{}`, `{0}`, or `{name}`, optionally
+/// followed by `:` and a spec like Rust's: fill and alignment (`<`, `^`, `>`), `0` padding, width,
+/// precision, and `x`, `X`, `b`, `o`, or `?` for the debug format. Use `{{` and `}}` for braces.
+pub fn fmt(mut args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if args.is_empty() {
+        bail!("`fmt` needs a template String");
+    }
+    let template = get_string(&args.remove(0), "fmt")?;
+
+    let mut out = String::new();
+    let mut next_pos = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{')=>{
+                chars.next();
+                out.push('{');
+            },
+            '}' if chars.peek() == Some(&'}')=>{
+                chars.next();
+                out.push('}');
+            },
+            '{'=>{
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}')=>break,
+                        Some(c)=>inner.push(c),
+                        None=>bail!("Unclosed `{{` in the `fmt` template"),
+                    }
+                }
+
+                let (arg, spec) = inner.split_once(':').unwrap_or((inner.as_str(), ""));
+                let spec = parse_fmt_spec(spec)?;
+                let dr = fmt_arg(arg.trim(), &args, &mut next_pos, state)?;
+                format_with_spec(&mut out, &dr, &spec, i, state)?;
+            },
+            '}'=>bail!("Unmatched `}}` in the `fmt` template. Use `}}}}` for a literal `}}`"),
+            c=>out.push(c),
+        }
+    }
+
+    return Ok(i.alloc(Data::String(out)));
+}
+
+pub fn chars(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     if args.len() != 1 {
         bail!("`chars` can only take one argument");
     }
@@ -115,7 +495,7 @@ pub fn chars(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<
     }
 }
 
-pub fn split(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<DataRef> {
+pub fn split(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
     if args.len() != 2 {
         bail!("`split` can only take two arguments");
     }
@@ -141,3 +521,284 @@ pub fn split(args: Vec<DataRef>, i: &mut Interpreter, _: &mut Interner)->Result<
         _=>bail!("`split` can only accept Strings"),
     }
 }
+
+fn get_string(dr: &DataRef, name: &str)->Result<String> {
+    match &*dr.get_data() {
+        Data::String(s)=>Ok(s.clone()),
+        _=>bail!("Type error: `{name}` only accepts Strings"),
+    }
+}
+
+/// Things like `find` and `replace` can look for a String or a Char
+fn get_pattern(dr: &DataRef, name: &str)->Result<String> {
+    match &*dr.get_data() {
+        Data::String(s)=>Ok(s.clone()),
+        Data::Char(c)=>Ok(c.to_string()),
+        _=>bail!("Type error: `{name}` can only look for a String or Char"),
+    }
+}
+
+/// All string indices are char indices, not byte indices. Negative indices count from the end,
+/// and the index just past the last char is allowed.
+fn char_index(dr: &DataRef, len: usize)->Result<usize> {
+    let n = match &*dr.get_data() {
+        Data::Number(n)=>*n,
+        _=>bail!("Type error: Strings can only be indexed with a Number"),
+    };
+
+    let idx = if n < 0 {
+        n + len as i64
+    } else {
+        n
+    };
+    if idx < 0 || idx > len as i64 {
+        bail!("Index out of bounds");
+    }
+
+    return Ok(idx as usize);
+}
+
+fn get_count(dr: &DataRef, name: &str)->Result<usize> {
+    match &*dr.get_data() {
+        Data::Number(n) if *n >= 0=>Ok(*n as usize),
+        Data::Number(_)=>bail!("`{name}` cannot take a negative count"),
+        _=>bail!("Type error: `{name}` takes a Number as the count"),
+    }
+}
+
+/// Converts a byte index from the std functions to a char index
+fn byte_to_char_index(s: &str, byte_idx: usize)->usize {
+    s[..byte_idx].chars().count()
+}
+
+/// `(substring s start)` or `(substring s start end)`. The end is not included.
+pub fn substring(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 2 && args.len() != 3 {
+        bail!("`substring` takes a String, a start, and an optional end");
+    }
+
+    let s = get_string(&args[0], "substring")?;
+    let len = s.chars().count();
+    let start = char_index(&args[1], len)?;
+    let end = match args.get(2) {
+        Some(end)=>char_index(end, len)?,
+        None=>len,
+    };
+    if start > end {
+        bail!("Substring start is after the end");
+    }
+
+    let sub = s.chars()
+        .skip(start)
+        .take(end - start)
+        .collect();
+
+    return Ok(i.alloc(Data::String(sub)));
+}
+
+/// Gives the char index of the first match, or `None`
+pub fn find(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "find")?;
+    let pat = get_pattern(&args[1], "find")?;
+
+    match s.find(pat.as_str()) {
+        Some(idx)=>Ok(i.alloc(Data::Number(byte_to_char_index(&s, idx) as i64))),
+        None=>Ok(i.alloc(Data::None)),
+    }
+}
+
+/// Gives the char index of the last match, or `None`
+pub fn rfind(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "rfind")?;
+    let pat = get_pattern(&args[1], "rfind")?;
+
+    match s.rfind(pat.as_str()) {
+        Some(idx)=>Ok(i.alloc(Data::Number(byte_to_char_index(&s, idx) as i64))),
+        None=>Ok(i.alloc(Data::None)),
+    }
+}
+
+/// `(replace s from to)` replaces every match
+pub fn replace(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "replace")?;
+    let from = get_pattern(&args[1], "replace")?;
+    let to = get_pattern(&args[2], "replace")?;
+
+    return Ok(i.alloc(Data::String(s.replace(from.as_str(), to.as_str()))));
+}
+
+pub fn trim(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "trim")?;
+    return Ok(i.alloc(Data::String(s.trim().to_string())));
+}
+
+pub fn trim_start(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "trimStart")?;
+    return Ok(i.alloc(Data::String(s.trim_start().to_string())));
+}
+
+pub fn trim_end(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "trimEnd")?;
+    return Ok(i.alloc(Data::String(s.trim_end().to_string())));
+}
+
+pub fn upper(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "upper")?;
+    return Ok(i.alloc(Data::String(s.to_uppercase())));
+}
+
+pub fn lower(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "lower")?;
+    return Ok(i.alloc(Data::String(s.to_lowercase())));
+}
+
+pub fn starts_with(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "startsWith")?;
+    let pat = get_pattern(&args[1], "startsWith")?;
+
+    return Ok(i.alloc(Data::Bool(s.starts_with(pat.as_str()))));
+}
+
+pub fn ends_with(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "endsWith")?;
+    let pat = get_pattern(&args[1], "endsWith")?;
+
+    return Ok(i.alloc(Data::Bool(s.ends_with(pat.as_str()))));
+}
+
+/// `(join list sep)`. The items are formatted the same as `toString`, so Chars are just the char.
+pub fn join(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    let items = match &*args[0].get_data() {
+        Data::List(items)=>items.clone(),
+        _=>bail!("Type error: `join` only accepts a List"),
+    };
+    let sep = get_pattern(&args[1], "join")?;
+
+    let mut out = String::new();
+    for (idx, item) in items.iter().enumerate() {
+        if idx > 0 {
+            out.push_str(sep.as_str());
+        }
+        if let Data::Char(c) = &*item.get_data() {
+            out.push(*c);
+            continue;
+        }
+        format_value(&mut out, item, i, state)?;
+    }
+
+    return Ok(i.alloc(Data::String(out)));
+}
+
+/// `(repeat s n)`
+pub fn repeat(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_pattern(&args[0], "repeat")?;
+    let count = get_count(&args[1], "repeat")?;
+    check_built_len(s.len().checked_mul(count), "repeat")?;
+
+    return Ok(i.alloc(Data::String(s.repeat(count))));
+}
+
+/// `None` means the length overflowed
+fn check_built_len(len: Option<usize>, name: &str)->Result<()> {
+    match len {
+        Some(len) if len <= MAX_BUILT_LEN=>Ok(()),
+        _=>bail!("`{name}` would make a String that is too long"),
+    }
+}
+
+/// The width is in chars, and the padding defaults to a space
+fn pad(args: &[DataRef], name: &str, left: bool)->Result<String> {
+    if args.len() != 2 && args.len() != 3 {
+        bail!("`{name}` takes a String, a width, and an optional Char to pad with");
+    }
+
+    let s = get_string(&args[0], name)?;
+    let width = get_count(&args[1], name)?;
+    let fill = match args.get(2).map(|dr|dr.get_data()).as_deref() {
+        Some(Data::Char(c))=>*c,
+        Some(_)=>bail!("Type error: `{name}` can only pad with a Char"),
+        None=>' ',
+    };
+
+    let len = s.chars().count();
+    if len >= width {
+        return Ok(s);
+    }
+
+    check_built_len((width - len).checked_mul(fill.len_utf8()), name)?;
+    let padding = fill.to_string().repeat(width - len);
+    if left {
+        return Ok(padding + s.as_str());
+    } else {
+        return Ok(s + padding.as_str());
+    }
+}
+
+/// `(padLeft s width)` or `(padLeft s width char)`
+pub fn pad_left(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = pad(&args, "padLeft", true)?;
+    return Ok(i.alloc(Data::String(s)));
+}
+
+/// `(padRight s width)` or `(padRight s width char)`
+pub fn pad_right(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = pad(&args, "padRight", false)?;
+    return Ok(i.alloc(Data::String(s)));
+}
+
+/// Splits on `\n` and `\r\n`
+pub fn lines(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "lines")?;
+    let list = s.lines()
+        .map(|line|i.alloc(Data::String(line.to_string())))
+        .collect();
+
+    return Ok(i.alloc(Data::List(list)));
+}
+
+/// `(parseInt s)` or `(parseInt s radix)`. Gives `None` if the String is not a number.
+pub fn parse_int(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    if args.len() != 1 && args.len() != 2 {
+        bail!("`parseInt` takes a String and an optional radix");
+    }
+
+    let s = get_string(&args[0], "parseInt")?;
+    let radix = match args.get(1) {
+        Some(radix)=>match &*radix.get_data() {
+            Data::Number(n) if (2..=36).contains(n)=>*n as u32,
+            Data::Number(_)=>bail!("`parseInt` radix must be between 2 and 36"),
+            _=>bail!("Type error: `parseInt` radix must be a Number"),
+        },
+        None=>10,
+    };
+
+    // underscores are allowed like in number literals, and numbers too big for a Number become
+    // a BigInt
+    let cleaned = s.trim().replace('_', "");
+    match BigInt::parse_bytes(cleaned.as_bytes(), radix) {
+        Some(n)=>Ok(i.alloc(Data::from_bigint(n))),
+        None=>Ok(i.alloc(Data::None)),
+    }
+}
+
+/// Gives `None` if the String is not a number
+pub fn parse_float(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = get_string(&args[0], "parseFloat")?;
+
+    match s.trim().parse::<f64>() {
+        Ok(f)=>Ok(i.alloc(Data::Float(f))),
+        Err(_)=>Ok(i.alloc(Data::None)),
+    }
+}
+
+/// Formats the value the same as `format`. Chars become a String with just that char.
+pub fn to_string(args: Vec<DataRef>, i: &mut Interpreter, state: &mut ConvertState)->Result<DataRef> {
+    if let Data::Char(c) = &*args[0].get_data() {
+        return Ok(i.alloc(Data::String(c.to_string())));
+    }
+
+    let mut out = String::new();
+    format_value(&mut out, &args[0], i, state)?;
+
+    return Ok(i.alloc(Data::String(out)));
+}
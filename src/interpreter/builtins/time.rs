@@ -0,0 +1,391 @@
+use anyhow::{
+    Result,
+    bail,
+};
+use std::{
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+    thread,
+};
+use super::{
+    Interpreter,
+    ConvertState,
+    Data,
+    DataRef,
+    NativeFn,
+    ArgCount,
+    arithmetic::as_f64,
+};
+
+
+pub const BUILTINS: &[(&str, NativeFn, ArgCount)] = &[
+    builtin!(now, 0),
+    builtin!(since, 1),
+    builtin!(timestamp, 0),
+    builtin!(sleep, 1),
+
+    builtin!(millis, 1),
+    builtin!(seconds, 1),
+    builtin!(minutes, 1),
+    builtin!(hours, 1),
+    builtin!(days, 1),
+    builtin!(to_millis, toMillis, 1),
+
+    builtin!(format, 1),
+    builtin!(parse, 1),
+];
+
+
+thread_local!(
+    /// `now` counts from here, so the numbers stay small enough for Floats to be precise
+    static START: Instant = Instant::now();
+);
+
+
+/// Durations are Floats of seconds, so they can be added, subtracted, and scaled with the normal
+/// arithmetic functions
+fn get_seconds(dr: &DataRef, name: &str)->Result<f64> {
+    match as_f64(&dr.get_data()) {
+        Some(f)=>Ok(f),
+        None=>bail!("Type error: `{name}` only accepts Numbers and Floats"),
+    }
+}
+
+/// Monotonic seconds. Only useful for measuring how long something took.
+pub fn now(_: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let secs = START.with(|start|start.elapsed().as_secs_f64());
+    return Ok(i.alloc(Data::Float(secs)));
+}
+
+/// `(since start)` gives the seconds since `start`, which came from `now`
+pub fn since(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let start = get_seconds(&args[0], "since")?;
+    let secs = START.with(|start|start.elapsed().as_secs_f64());
+
+    return Ok(i.alloc(Data::Float(secs - start)));
+}
+
+/// Wall-clock seconds since the Unix epoch. Negative if the system clock is before 1970.
+pub fn timestamp(_: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let secs = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d)=>d.as_secs_f64(),
+        Err(e)=>-e.duration().as_secs_f64(),
+    };
+
+    return Ok(i.alloc(Data::Float(secs)));
+}
+
+pub fn sleep(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let secs = get_seconds(&args[0], "sleep")?;
+    let Ok(duration) = Duration::try_from_secs_f64(secs) else {
+        bail!("`sleep` cannot sleep for {secs} seconds");
+    };
+    thread::sleep(duration);
+
+    return Ok(i.alloc(Data::None));
+}
+
+fn scale(args: &[DataRef], name: &str, secs: f64, i: &mut Interpreter)->Result<DataRef> {
+    let n = get_seconds(&args[0], name)?;
+    return Ok(i.alloc(Data::Float(n * secs)));
+}
+
+pub fn millis(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return scale(&args, "millis", 0.001, i);
+}
+
+pub fn seconds(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return scale(&args, "seconds", 1.0, i);
+}
+
+pub fn minutes(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return scale(&args, "minutes", 60.0, i);
+}
+
+pub fn hours(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return scale(&args, "hours", 3600.0, i);
+}
+
+pub fn days(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    return scale(&args, "days", 86400.0, i);
+}
+
+/// Rounds to the nearest whole millisecond and gives a Number
+pub fn to_millis(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let secs = get_seconds(&args[0], "toMillis")?;
+    if !secs.is_finite() {
+        bail!("`toMillis` cannot convert {secs} to a Number");
+    }
+
+    return Ok(i.alloc(Data::Number((secs * 1000.0).round() as i64)));
+}
+
+/// Formats a timestamp from `timestamp` as an RFC 3339 date in UTC, like
+/// `2024-03-01T12:30:00.25Z`. Fractional seconds are only written if there are any, and are
+/// rounded to the microsecond because a Float timestamp isn't much more precise than that.
+pub fn format(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let secs = get_seconds(&args[0], "format")?;
+    return Ok(i.alloc(Data::String(format_rfc3339(secs)?)));
+}
+
+/// Parses an RFC 3339 date, like `2024-03-01T12:30:00Z` or `2024-03-01T14:30:00+02:00`, into a
+/// timestamp. Offsets are converted to UTC.
+pub fn parse(args: Vec<DataRef>, i: &mut Interpreter, _: &mut ConvertState)->Result<DataRef> {
+    let s = match &*args[0].get_data() {
+        Data::String(s)=>s.clone(),
+        _=>bail!("Type error: `parse` only accepts Strings"),
+    };
+
+    let Some(secs) = parse_rfc3339(&s) else {
+        bail!("`parse` got an invalid RFC 3339 date: {s:?}");
+    };
+
+    return Ok(i.alloc(Data::Float(secs)));
+}
+
+
+/// Days since 1970-01-01 to `(year, month, day)`. From Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64)->(i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 {mp + 3} else {mp - 9}) as u32;
+    let year = yoe + era * 400 + if month <= 2 {1} else {0};
+
+    return (year, month, day);
+}
+
+/// The opposite of `civil_from_days`
+fn days_from_civil(year: i64, month: u32, day: u32)->i64 {
+    let year = if month <= 2 {year - 1} else {year};
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let mp = if month > 2 {month - 3} else {month + 9};
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    return era * 146097 + doe - 719468;
+}
+
+fn days_in_month(year: i64, month: u32)->u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)=>29,
+        2=>28,
+        4|6|9|11=>30,
+        _=>31,
+    }
+}
+
+/// The opposite of `parse_rfc3339`, always in UTC
+fn format_rfc3339(secs: f64)->Result<String> {
+    if !secs.is_finite() {
+        bail!("`format` cannot format {secs} as a date");
+    }
+
+    let whole = secs.floor();
+    let micros = ((secs - whole) * 1e6).round() as u32;
+    let (whole, micros) = if micros >= 1_000_000 {
+        (whole as i64 + 1, 0)
+    } else {
+        (whole as i64, micros)
+    };
+
+    let days = whole.div_euclid(86400);
+    let day_secs = whole.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    if !(0..=9999).contains(&year) {
+        bail!("`format` can only format years between 0 and 9999");
+    }
+
+    let mut out = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        day_secs / 3600,
+        (day_secs / 60) % 60,
+        day_secs % 60,
+    );
+    if micros > 0 {
+        let frac = format!("{micros:06}");
+        out.push('.');
+        out.push_str(frac.trim_end_matches('0'));
+    }
+    out.push('Z');
+
+    return Ok(out);
+}
+
+/// `None` if the date is not valid
+fn parse_rfc3339(s: &str)->Option<f64> {
+    let bytes = s.as_bytes();
+    let num = |start: usize, len: usize|->Option<u32> {
+        let digits = bytes.get(start..start + len)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        return s[start..start + len].parse().ok();
+    };
+    let expect = |idx: usize, chars: &[u8]|bytes.get(idx).is_some_and(|b|chars.contains(b));
+
+    let year = num(0, 4)? as i64;
+    let month = num(5, 2)?;
+    let day = num(8, 2)?;
+    let hour = num(11, 2)?;
+    let minute = num(14, 2)?;
+    let second = num(17, 2)?;
+    if !(expect(4, b"-") && expect(7, b"-") && expect(10, b"Tt ") && expect(13, b":") && expect(16, b":")) {
+        return None;
+    }
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    // 60 is allowed for leap seconds, which we fold into the next minute
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut idx = 19;
+    let mut frac = 0.0;
+    if expect(idx, b".") {
+        let digits = bytes[idx + 1..].iter().take_while(|b|b.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        frac = s[idx..idx + 1 + digits].parse::<f64>().ok()?;
+        idx += 1 + digits;
+    }
+
+    let offset = match bytes.get(idx)? {
+        b'Z'|b'z'=>{
+            idx += 1;
+            0
+        },
+        sign@(b'+'|b'-')=>{
+            let off_hour = num(idx + 1, 2)? as i64;
+            let off_minute = num(idx + 4, 2)? as i64;
+            if !expect(idx + 3, b":") || off_hour > 23 || off_minute > 59 {
+                return None;
+            }
+            idx += 6;
+            let offset = off_hour * 3600 + off_minute * 60;
+            if *sign == b'+' {offset} else {-offset}
+        },
+        _=>return None,
+    };
+    if idx != bytes.len() {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64 - offset;
+
+    return Some(secs as f64 + frac);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-03-01T12:30:00Z
+    const MARCH_FIRST: f64 = 1709296200.0;
+
+    #[test]
+    fn civil_days_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(days_from_civil(2000, 2, 29), 11016);
+
+        for days in (-800_000..800_000).step_by(97) {
+            let (year, month, day) = civil_from_days(days);
+            assert!((1..=days_in_month(year, month)).contains(&day));
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn parse_offsets() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(parse_rfc3339("2024-03-01T12:30:00Z"), Some(MARCH_FIRST));
+        assert_eq!(parse_rfc3339("2024-03-01T14:30:00+02:00"), Some(MARCH_FIRST));
+        assert_eq!(parse_rfc3339("2024-03-01T07:00:00-05:30"), Some(MARCH_FIRST));
+        assert_eq!(parse_rfc3339("2024-03-01t12:30:00z"), Some(MARCH_FIRST));
+        assert_eq!(parse_rfc3339("2024-03-01 12:30:00+00:00"), Some(MARCH_FIRST));
+        assert_eq!(parse_rfc3339("2024-03-01T00:30:00-00:00"), Some(MARCH_FIRST - 43200.0));
+    }
+
+    #[test]
+    fn parse_fractional_seconds() {
+        assert_eq!(parse_rfc3339("2024-03-01T12:30:00.25Z"), Some(MARCH_FIRST + 0.25));
+        assert_eq!(parse_rfc3339("2024-03-01T14:30:00.5+02:00"), Some(MARCH_FIRST + 0.5));
+        assert_eq!(parse_rfc3339("1969-12-31T23:59:59.875Z"), Some(-0.125));
+        assert_eq!(parse_rfc3339("2024-03-01T12:30:00.Z"), None);
+    }
+
+    #[test]
+    fn parse_leap_seconds() {
+        // Folded into the first second of the next minute
+        let new_year = parse_rfc3339("2017-01-01T00:00:00Z");
+        assert_eq!(parse_rfc3339("2016-12-31T23:59:60Z"), new_year);
+        assert_eq!(parse_rfc3339("2016-12-31T18:59:60-05:00"), new_year);
+        assert_eq!(parse_rfc3339("2016-12-31T23:59:61Z"), None);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_dates() {
+        assert!(parse_rfc3339("2024-02-29T00:00:00Z").is_some());
+        assert!(parse_rfc3339("2000-02-29T00:00:00Z").is_some());
+
+        for s in [
+            "2023-02-29T00:00:00Z",
+            "1900-02-29T00:00:00Z",
+            "2024-04-31T00:00:00Z",
+            "2024-13-01T00:00:00Z",
+            "2024-00-01T00:00:00Z",
+            "2024-03-00T00:00:00Z",
+            "2024-03-01T24:00:00Z",
+            "2024-03-01T12:60:00Z",
+            "2024-03-01T12:30:00",
+            "2024-03-01T12:30:00+0200",
+            "2024-03-01T12:30:00+24:00",
+            "2024-03-01T12:30:00Zjunk",
+            "2024-3-01T12:30:00Z",
+            "2024-03-01",
+            "",
+        ] {
+            assert_eq!(parse_rfc3339(s), None, "{s:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn format_dates() {
+        assert_eq!(format_rfc3339(0.0).unwrap(), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(MARCH_FIRST).unwrap(), "2024-03-01T12:30:00Z");
+        assert_eq!(format_rfc3339(MARCH_FIRST + 0.25).unwrap(), "2024-03-01T12:30:00.25Z");
+        assert_eq!(format_rfc3339(-0.5).unwrap(), "1969-12-31T23:59:59.5Z");
+        assert_eq!(format_rfc3339(MARCH_FIRST + 0.1).unwrap(), "2024-03-01T12:30:00.1Z");
+        assert_eq!(format_rfc3339(MARCH_FIRST + 0.001).unwrap(), "2024-03-01T12:30:00.001Z");
+        assert_eq!(format_rfc3339(MARCH_FIRST + 0.9999999).unwrap(), "2024-03-01T12:30:01Z");
+        assert_eq!(format_rfc3339(MARCH_FIRST + 1e-8).unwrap(), "2024-03-01T12:30:00Z");
+        assert_eq!(format_rfc3339(253402300799.0).unwrap(), "9999-12-31T23:59:59Z");
+
+        assert!(format_rfc3339(253402300800.0).is_err());
+        assert!(format_rfc3339(f64::NAN).is_err());
+        assert!(format_rfc3339(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn format_parse_round_trip() {
+        for secs in [0.0, MARCH_FIRST, MARCH_FIRST + 0.25, MARCH_FIRST + 0.1, -86400.125, 951782400.0, 4102444800.5] {
+            let s = format_rfc3339(secs).unwrap();
+            assert_eq!(parse_rfc3339(&s), Some(secs), "{s}");
+        }
+    }
+}
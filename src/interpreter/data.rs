@@ -1,7 +1,20 @@
 #![allow(unsafe_code)]
 
 use rustc_hash::FxBuildHasher;
-use indexmap::IndexSet;
+use regex::Regex;
+use num_bigint::BigInt;
+use num_traits::{
+    ToPrimitive,
+    FromPrimitive,
+};
+use anyhow::{
+    Result,
+    bail,
+};
+use indexmap::{
+    IndexSet,
+    IndexMap,
+};
 use std::{
     cell::{
         RefCell,
@@ -17,6 +30,7 @@ use std::{
     io::{
         Stdin,
         BufReader,
+        BufWriter,
     },
     hash::{
         Hasher,
@@ -29,13 +43,22 @@ use std::{
     ptr::NonNull,
     mem,
 };
+use crate::typed_array::{
+    TypedArray,
+    Element,
+};
 use super::{
+    builtins::random::Rng,
+    persistent::{
+        PVec,
+        PMap,
+    },
     ArgCount,
     CallStack,
     Scopes,
     // Metrics,
     NativeFn,
-    IdentMap,
+    ObjectMap,
     DEBUG,
     ast::*,
 };
@@ -43,6 +66,13 @@ use super::{
 
 type DataRefSet = IndexSet<HashableDataRef, FxBuildHasher>;
 
+/// The backing storage for `Data::Set`. The original `DataRef` is kept alongside the key so we can
+/// give back the exact value that was inserted.
+pub type DataSet = IndexMap<SetKey, DataRef, FxBuildHasher>;
+
+/// The backing storage for `Data::PMap`. Like `DataSet`, the original key is kept next to the value.
+pub type DataPMap = PMap<SetKey, (DataRef, DataRef)>;
+
 
 thread_local!(
     pub static ALLOCATIONS: RefCell<usize> = const {RefCell::new(0)};
@@ -52,18 +82,34 @@ thread_local!(
 
 #[derive(Debug, Clone)]
 pub enum NativeData {
-    File(Rc<RefCell<BufReader<File>>>),
+    /// A file opened for reading. `None` once it is closed.
+    File(Rc<RefCell<Option<BufReader<File>>>>),
+    /// A file opened with `.write` or `.append`. `None` once it is closed.
+    WriteFile(Rc<RefCell<Option<BufWriter<File>>>>),
     Stdout,
+    Stderr,
     Stdin(Rc<RefCell<BufReader<Stdin>>>),
+    /// Compiled once and shared by every copy
+    Regex(Rc<Regex>),
+    /// A generator from `std/random`. Copies share the same state.
+    Random(Rc<RefCell<Rng>>),
 }
 impl PartialEq for NativeData {
     fn eq(&self, other: &Self)->bool {
         match (self, other) {
-            (Self::File(f1), Self::File(f2))=>{
-                f1.borrow_mut().get_mut().as_raw_fd() == f2.borrow_mut().get_mut().as_raw_fd()
+            (Self::File(f1), Self::File(f2))=>match (&*f1.borrow(), &*f2.borrow()) {
+                (Some(f1), Some(f2))=>f1.get_ref().as_raw_fd() == f2.get_ref().as_raw_fd(),
+                _=>Rc::ptr_eq(f1, f2),
+            },
+            (Self::WriteFile(f1), Self::WriteFile(f2))=>match (&*f1.borrow(), &*f2.borrow()) {
+                (Some(f1), Some(f2))=>f1.get_ref().as_raw_fd() == f2.get_ref().as_raw_fd(),
+                _=>Rc::ptr_eq(f1, f2),
             },
             (Self::Stdout, Self::Stdout)=>true,
+            (Self::Stderr, Self::Stderr)=>true,
             (Self::Stdin(_), Self::Stdin(_))=>true,
+            (Self::Regex(r1), Self::Regex(r2))=>r1.as_str() == r2.as_str(),
+            (Self::Random(r1), Self::Random(r2))=>Rc::ptr_eq(r1, r2),
             _=>false,
         }
     }
@@ -72,13 +118,24 @@ impl PartialEq for NativeData {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Data {
     List(Vec<DataRef>),
-    Object(IdentMap<DataRef>),
+    Object(ObjectMap),
+    Set(DataSet),
+    /// Persistent vector. Never mutated in place; "changing" it makes a new one that shares most of
+    /// its structure with the old one.
+    PVec(PVec<DataRef>),
+    /// Persistent hash map. Same rules as `PVec`.
+    PMap(DataPMap),
+    /// A flat array of `f64`, `i64`, or `u8`. Made by `std/array`.
+    TypedArray(TypedArray),
 
     Ident(Ident),
     Number(i64),
+    /// Only for integers that don't fit in a `Number`. Make these with `Data::from_bigint`.
+    BigInt(BigInt),
     Float(f64),
     String(String),
     Char(char),
+    Byte(u8),
     Bool(bool),
 
     Fn(FnId),
@@ -87,12 +144,34 @@ pub enum Data {
         id: FnId,
         captures: ClosureCaptures,
     },
+    /// A function with some of its arguments already given. Made by `std/seq/partial`.
+    Partial {
+        func: DataRef,
+        args: Vec<DataRef>,
+    },
+    /// Functions called right to left, each getting the result of the last. Made by `std/seq/comp`.
+    Composed(Vec<DataRef>),
 
     NativeData(NativeData),
+    /// An iterator over a built-in value
+    Iterator(NativeIter),
+    /// A sequence that is realized as it is iterated
+    LazySeq(LazySeq),
 
+    /// Returned by iterators when they have no more items. Unlike `None`, it can't be an item.
+    Done,
     None,
 }
 impl Data {
+    /// BigInts that fit in a `Number` are turned back into one, so each integer only has one
+    /// representation
+    pub fn from_bigint(n: BigInt)->Self {
+        match n.to_i64() {
+            Some(n)=>Self::Number(n),
+            None=>Self::BigInt(n),
+        }
+    }
+
     pub fn add_data_refs(&self, refs: &mut DataRefSet) {
         match self {
             Self::List(items)=>refs.extend(items.iter()
@@ -103,11 +182,35 @@ impl Data {
                 .cloned()
                 .map(HashableDataRef)
             ),
+            Self::Set(items)=>refs.extend(items.values()
+                .cloned()
+                .map(HashableDataRef)
+            ),
+            Self::PVec(items)=>items.for_each(|item|{
+                refs.insert(HashableDataRef(item.clone()));
+            }),
+            Self::PMap(map)=>map.for_each(|_, (key, value)|{
+                refs.insert(HashableDataRef(key.clone()));
+                refs.insert(HashableDataRef(value.clone()));
+            }),
             Self::Closure{captures,..}=>refs.extend(captures.0.iter()
                 .cloned()
                 .map(|(_,c)|c)
                 .map(HashableDataRef)
             ),
+            Self::Partial{func, args}=>{
+                refs.insert(HashableDataRef(func.clone()));
+                refs.extend(args.iter()
+                    .cloned()
+                    .map(HashableDataRef)
+                );
+            },
+            Self::Composed(funcs)=>refs.extend(funcs.iter()
+                .cloned()
+                .map(HashableDataRef)
+            ),
+            Self::Iterator(iter)=>iter.add_data_refs(refs),
+            Self::LazySeq(seq)=>seq.add_data_refs(refs),
             _=>{},
         }
     }
@@ -121,24 +224,274 @@ impl Data {
                 Self::Number(_)|
                 Self::Float(_)|
                 Self::Char(_)|
+                Self::Byte(_)|
                 Self::Bool(_)|
                 Self::Fn(_)|
                 Self::NativeFn(..)|
                 Self::NativeData(_)|    // technically wrong, but I don't care, and they are Rc'd
                                         // so it doesn't matter much anyways
+                Self::PVec(_)|          // the nodes are shared between versions, so we can't
+                Self::PMap(_)|          // really say who owns them
+                Self::Done|
                 Self::None=>{},
 
             Self::Closure{captures,..}=>alloc_size += captures.0.capacity() * mem::size_of::<(Ident, DataRef)>(),
+            Self::Partial{args,..}=>alloc_size += args.capacity() * mem::size_of::<DataRef>(),
+            Self::Composed(funcs)=>alloc_size += funcs.capacity() * mem::size_of::<DataRef>(),
+            Self::Iterator(iter)=>alloc_size += iter.allocation_size(),
+            Self::LazySeq(seq)=>alloc_size += seq.cache.capacity() * mem::size_of::<DataRef>(),
 
             Self::String(s)=>alloc_size += s.capacity(),
+            Self::TypedArray(arr)=>alloc_size += arr.byte_capacity(),
+            Self::BigInt(n)=>alloc_size += n.iter_u64_digits().len() * mem::size_of::<u64>(),
             Self::List(items)=>alloc_size += items.capacity() * mem::size_of::<DataRef>(),
             Self::Object(fields)=>alloc_size += fields.capacity() * mem::size_of::<(Ident, DataRef)>(),
+            Self::Set(items)=>alloc_size += items.capacity() * mem::size_of::<(SetKey, DataRef)>(),
         }
 
         return alloc_size;
     }
 }
 
+impl From<Element> for Data {
+    fn from(element: Element)->Self {
+        match element {
+            Element::Float(f)=>Self::Float(f),
+            Element::Int(i)=>Self::Number(i),
+            Element::Byte(b)=>Self::Byte(b),
+        }
+    }
+}
+
+
+/// The state of an iterator over a built-in value
+#[derive(Debug, Clone, PartialEq)]
+pub enum NativeIter {
+    /// Indexes into the list as it goes, so items pushed while iterating are seen
+    List {
+        list: DataRef,
+        idx: usize,
+    },
+    Chars {
+        chars: Vec<char>,
+        idx: usize,
+    },
+    /// Indexes into the typed array as it goes, same as `List`
+    Array {
+        array: DataRef,
+        idx: usize,
+    },
+    /// A snapshot of the items of a set, vector, map, or object
+    Items {
+        items: Vec<DataRef>,
+        idx: usize,
+    },
+    /// Goes on forever if there is no end, or until the next number would overflow. `next` is
+    /// `None` once it has overflowed.
+    Range {
+        next: Option<i64>,
+        end: Option<i64>,
+        step: i64,
+    },
+    /// Calls the function until it returns `done`
+    Func(DataRef),
+    /// Reads from a lazy sequence, realizing items as needed
+    Seq {
+        seq: DataRef,
+        idx: usize,
+    },
+    /// Gives `cur`, then `(func cur)`, and so on forever
+    Iterate {
+        func: DataRef,
+        cur: DataRef,
+        started: bool,
+    },
+    /// Goes on forever if there is no count
+    Repeat {
+        item: DataRef,
+        remaining: Option<usize>,
+    },
+    Take {
+        inner: DataRef,
+        remaining: usize,
+    },
+    /// Skips `count` items of the inner iterator the first time it is advanced
+    Skip {
+        inner: DataRef,
+        count: usize,
+    },
+    TakeWhile {
+        inner: DataRef,
+        pred: DataRef,
+        done: bool,
+    },
+    /// Gives `(func item)` for each item of the inner iterator
+    Map {
+        inner: DataRef,
+        func: DataRef,
+    },
+    /// Skips the items of the inner iterator that `pred` gives `false` for
+    Filter {
+        inner: DataRef,
+        pred: DataRef,
+    },
+}
+impl NativeIter {
+    pub fn add_data_refs(&self, refs: &mut DataRefSet) {
+        match self {
+            Self::List{list,..}=>{
+                refs.insert(HashableDataRef(list.clone()));
+            },
+            Self::Array{array,..}=>{
+                refs.insert(HashableDataRef(array.clone()));
+            },
+            Self::Items{items,..}=>refs.extend(items.iter()
+                .cloned()
+                .map(HashableDataRef)
+            ),
+            Self::Func(func)=>{
+                refs.insert(HashableDataRef(func.clone()));
+            },
+            Self::Seq{seq,..}=>{
+                refs.insert(HashableDataRef(seq.clone()));
+            },
+            Self::Iterate{func, cur,..}=>{
+                refs.insert(HashableDataRef(func.clone()));
+                refs.insert(HashableDataRef(cur.clone()));
+            },
+            Self::Repeat{item,..}=>{
+                refs.insert(HashableDataRef(item.clone()));
+            },
+            Self::Take{inner,..}|Self::Skip{inner,..}=>{
+                refs.insert(HashableDataRef(inner.clone()));
+            },
+            Self::TakeWhile{inner, pred,..}|Self::Filter{inner, pred}=>{
+                refs.insert(HashableDataRef(inner.clone()));
+                refs.insert(HashableDataRef(pred.clone()));
+            },
+            Self::Map{inner, func}=>{
+                refs.insert(HashableDataRef(inner.clone()));
+                refs.insert(HashableDataRef(func.clone()));
+            },
+            Self::Chars{..}|Self::Range{..}=>{},
+        }
+    }
+
+    pub fn allocation_size(&self)->usize {
+        match self {
+            Self::Chars{chars,..}=>chars.capacity() * mem::size_of::<char>(),
+            Self::Items{items,..}=>items.capacity() * mem::size_of::<DataRef>(),
+            _=>0,
+        }
+    }
+}
+
+/// Items that have been realized are kept in `cache`, so the sequence can be iterated more than
+/// once without running anything again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LazySeq {
+    pub cache: Vec<DataRef>,
+    pub source: LazySource,
+}
+impl LazySeq {
+    pub fn new(source: LazySource)->Self {
+        LazySeq {
+            cache: Vec::new(),
+            source,
+        }
+    }
+
+    pub fn add_data_refs(&self, refs: &mut DataRefSet) {
+        refs.extend(self.cache.iter()
+            .cloned()
+            .map(HashableDataRef)
+        );
+        match &self.source {
+            LazySource::Thunk(dr)|LazySource::Iter(dr)|LazySource::Slice{seq: dr,..}=>{
+                refs.insert(HashableDataRef(dr.clone()));
+            },
+            LazySource::Done=>{},
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LazySource {
+    /// Called once when the first item is needed. It gives something to iterate over.
+    Thunk(DataRef),
+    Iter(DataRef),
+    /// Reads the items of another lazy sequence from `start`, up to `len` of them if there is a
+    /// `len`. The items stay in that sequence's cache, so they aren't cached twice.
+    Slice {
+        seq: DataRef,
+        start: usize,
+        len: Option<usize>,
+    },
+    Done,
+}
+
+
+/// A hashable snapshot of a value used as the key in a `Data::Set`. Only primitive values and lists
+/// of them can be hashed. Lists are hashed by their contents at the time they are inserted, so
+/// mutating a list after putting it in a set does not change where the set thinks it is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SetKey {
+    Ident(Ident),
+    Number(i64),
+    /// Only for BigInts that don't fit in a `Number`
+    BigInt(BigInt),
+    /// Stored as the bits of the float. Whole Floats are stored as the integer they equal, and all
+    /// NaNs are the same NaN so equality stays consistent with hashing.
+    Float(u64),
+    String(String),
+    Char(char),
+    Byte(u8),
+    Bool(bool),
+    List(Vec<SetKey>),
+    None,
+}
+impl SetKey {
+    pub fn from_data(data: &Data)->Result<Self> {
+        match data {
+            Data::Ident(i)=>Ok(Self::Ident(*i)),
+            Data::Number(n)=>Ok(Self::Number(*n)),
+            Data::BigInt(n)=>Ok(Self::from_bigint(n.clone())),
+            Data::Float(f)=>{
+                // `=` compares numbers by value, so `1.0` has to be the same key as `1`. This also
+                // makes `-0.0` the same as `0.0`.
+                if f.fract() == 0.0 {
+                    if let Some(n) = BigInt::from_f64(*f) {
+                        return Ok(Self::from_bigint(n));
+                    }
+                }
+                let f = if f.is_nan() {f64::NAN} else {*f};
+
+                Ok(Self::Float(f.to_bits()))
+            },
+            Data::String(s)=>Ok(Self::String(s.clone())),
+            Data::Char(c)=>Ok(Self::Char(*c)),
+            Data::Byte(b)=>Ok(Self::Byte(*b)),
+            Data::Bool(b)=>Ok(Self::Bool(*b)),
+            Data::None=>Ok(Self::None),
+            Data::List(items)=>{
+                let mut keys = Vec::with_capacity(items.len());
+                for item in items.iter() {
+                    keys.push(Self::from_data(&item.get_data())?);
+                }
+
+                Ok(Self::List(keys))
+            },
+            _=>bail!("Type error: Only primitive values and lists can be hashed"),
+        }
+    }
+
+    fn from_bigint(n: BigInt)->Self {
+        match n.to_i64() {
+            Some(n)=>Self::Number(n),
+            None=>Self::BigInt(n),
+        }
+    }
+}
 
 #[derive(Clone, PartialEq)]
 pub struct ClosureCaptures(pub Vec<(Ident, DataRef)>);
@@ -231,6 +584,7 @@ impl DataRef {
             std::ptr::write(raw_ptr, DataBox {
                 inner: RefCell::new(data),
                 pinned: Cell::new(false),
+                frozen: Cell::new(false),
                 external: RefCell::new(0),
                 generation: Cell::new(0),
             });
@@ -270,6 +624,17 @@ impl DataRef {
         self.get_data_box().inner.borrow_mut()
     }
 
+    /// Same as `get_data_mut`, but errors if the data is frozen. Anything that mutates a value
+    /// in place on behalf of the program should use this.
+    #[inline]
+    pub fn try_get_data_mut<'a>(&'a mut self)->Result<RefMut<'a, Data>> {
+        if self.is_frozen() {
+            bail!("Cannot mutate a frozen value");
+        }
+
+        Ok(self.get_data_mut())
+    }
+
     #[inline]
     pub fn get_generation(&self)->u64 {
         self.get_data_box().generation.get()
@@ -311,6 +676,36 @@ impl DataRef {
         self.get_data_box().pinned.get()
     }
 
+    #[inline]
+    pub fn set_frozen(&self) {
+        self.get_data_box().frozen.set(true);
+    }
+
+    #[inline]
+    pub fn is_frozen(&self)->bool {
+        self.get_data_box().frozen.get()
+    }
+
+    /// Freeze this data and everything reachable from it. Closures are frozen themselves, but
+    /// their captures are left alone so they can keep their own state.
+    pub fn freeze(&self) {
+        let mut todo_list = DataRefSet::default();
+        todo_list.insert(self.clone().hashable());
+
+        while let Some(item) = todo_list.pop() {
+            if item.0.is_frozen() {
+                continue;
+            }
+            item.0.set_frozen();
+
+            let data = item.0.get_data();
+            match &*data {
+                Data::Closure{..}=>{},
+                _=>data.add_data_refs(&mut todo_list),
+            }
+        }
+    }
+
     #[inline]
     pub fn is_same(&self, other: &Self)->bool {
         self.inner == other.inner
@@ -347,6 +742,7 @@ impl DataRef {
 struct DataBox {
     inner: RefCell<Data>,
     pinned: Cell<bool>,
+    frozen: Cell<bool>,
     external: RefCell<usize>,
     generation: Cell<u64>,
 }
@@ -355,6 +751,7 @@ impl Clone for DataBox {
         DataBox {
             inner: self.inner.clone(),
             pinned: Cell::new(false),
+            frozen: Cell::new(false),
             external: RefCell::new(0),
             generation: Cell::new(0),
         }
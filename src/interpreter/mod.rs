@@ -1,4 +1,8 @@
 use rustc_hash::FxBuildHasher;
+use indexmap::{
+    IndexMap,
+    IndexSet,
+};
 use anyhow::{
     // Context,
     Result,
@@ -10,10 +14,7 @@ use std::{
         Duration,
         Instant,
     },
-    collections::{
-        HashMap,
-        HashSet,
-    },
+    collections::HashMap,
     io::{
         BufReader,
         stdin,
@@ -29,6 +30,7 @@ use data::*;
 pub mod ast;
 mod builtins;
 pub mod data;
+mod persistent;
 // mod new_data;
 // mod perfect_hasher;
 
@@ -36,10 +38,14 @@ pub mod data;
 pub type CallStack = Stack<(InstructionId, Scopes)>;
 pub type Scopes = Stack<ScopeItem>;
 
-pub type NativeFn = fn(Vec<DataRef>, &mut Interpreter, &mut Interner)->Result<DataRef>;
+pub type NativeFn = fn(Vec<DataRef>, &mut Interpreter, &mut ConvertState)->Result<DataRef>;
 
 pub type IdentMap<T> = HashMap<Ident, T, FxBuildHasher>;
-pub type IdentSet = HashSet<Ident, FxBuildHasher>;
+/// Insertion-ordered so the variables in a scope (and the modules made from them) keep the order
+/// they were defined in.
+pub type IdentSet = IndexSet<Ident, FxBuildHasher>;
+/// Object fields are kept in insertion order so printing, `fields`, etc. are the same every run.
+pub type ObjectMap = IndexMap<Ident, DataRef, FxBuildHasher>;
 
 // pub type FnIdMap<T> = HashMap<FnId, T, FxBuildHasher<Ident>>;
 // pub type FnIdSet = HashSet<FnId, FxBuildHasher<Ident>>;
@@ -49,6 +55,8 @@ pub type IdentSet = HashSet<Ident, FxBuildHasher>;
 
 
 const DEBUG: bool = false;
+/// How many `$` parents a vtable lookup follows before assuming there is a cycle
+const MAX_VTABLE_DEPTH: usize = 64;
 
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -133,14 +141,19 @@ impl Env {
         }
     }
 
-    pub fn into_root_scope(mut self)->IdentMap<DataRef> {
+    pub fn into_root_scope(mut self)->ObjectMap {
         while self.scopes.len() > 1 {
             self.pop_scope();
         }
 
-        let mut out = IdentMap::default();
-        out.reserve(self.vars.capacity());
-        for (i, mut scope) in self.vars.drain() {
+        let root_scope = self.scopes.pop().unwrap_or_default();
+
+        // go through the root scope instead of `vars` so the module fields are in the order they
+        // were defined
+        let mut out = ObjectMap::default();
+        out.reserve(root_scope.len());
+        for i in root_scope {
+            let Some(mut scope) = self.vars.remove(&i) else {continue};
             if scope.len() == 1 {
                 out.insert(i, scope.pop().unwrap().inner());
             } else if scope.len() > 1 {
@@ -258,12 +271,29 @@ pub struct Metrics {
     pub max_allocation_bytes: u64,
 }
 
+/// Names of the vtable methods that builtins use when an operand is an object
+#[derive(Debug, Copy, Clone)]
+pub struct ProtocolIdents {
+    pub add: Ident,
+    pub eq: Ident,
+    pub lt: Ident,
+    pub fmt: Ident,
+    pub hash: Ident,
+    pub iter: Ident,
+    pub next: Ident,
+}
+
 pub struct Interpreter {
     env_stack: Stack<Env>,
     old_envs: Stack<Env>,
     root_env: Env,
     recur_ident: Ident,
     vtable_ident: Ident,
+    type_ident: Ident,
+    consts_ident: Ident,
+    /// What f-strings call, so they don't depend on `std`
+    fmt_fn: DataRef,
+    pub protocol: ProtocolIdents,
     call_stack: CallStack,
     scopes: Scopes,
     var_count: usize,
@@ -294,7 +324,9 @@ impl Interpreter {
     pub fn new<'a>(state: &mut ConvertState)->Self {
         let mut root_env = Env::new();
         root_env.push_scope();
-        let data = DataStore::new();
+        let mut data = DataStore::new();
+        let fmt_fn = data.insert(Data::NativeFn("fmt", builtins::string::fmt, ArgCount::Any));
+        fmt_fn.set_pinned();
 
         // println!("Line: {}", line!());
 
@@ -310,6 +342,18 @@ impl Interpreter {
             data,
             recur_ident: state.interner.intern("recur"),
             vtable_ident: state.interner.intern("$"),
+            type_ident: state.interner.intern("$type"),
+            consts_ident: state.interner.intern("$consts"),
+            fmt_fn,
+            protocol: ProtocolIdents {
+                add: state.interner.intern("$add"),
+                eq: state.interner.intern("$eq"),
+                lt: state.interner.intern("$lt"),
+                fmt: state.interner.intern("$fmt"),
+                hash: state.interner.intern("$hash"),
+                iter: state.interner.intern("$iter"),
+                next: state.interner.intern("$next"),
+            },
             call_stack: Stack::new(),
             scopes: Stack::new(),
             metrics: Metrics::default(),
@@ -325,7 +369,7 @@ impl Interpreter {
     }
 
     fn insert_builtins(&mut self, state: &mut ConvertState) {
-        let mut core_object = IdentMap::default();
+        let mut core_object = ObjectMap::default();
         for (name, func, arg_count) in builtins::core::BUILTINS.into_iter() {
             let ident = state.interner.intern(*name);
             let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
@@ -342,7 +386,7 @@ impl Interpreter {
             self.root_env.insert(ident, data);
         }
 
-        let mut string_object = IdentMap::default();
+        let mut string_object = ObjectMap::default();
         for (name, func, arg_count) in builtins::string::BUILTINS.into_iter() {
             let ident = state.interner.intern(*name);
             let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
@@ -350,7 +394,7 @@ impl Interpreter {
             string_object.insert(ident, data);
         }
 
-        let mut misc_object = IdentMap::default();
+        let mut misc_object = ObjectMap::default();
         for (name, func, arg_count) in builtins::misc::BUILTINS.into_iter() {
             let ident = state.interner.intern(*name);
             let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
@@ -358,7 +402,7 @@ impl Interpreter {
             misc_object.insert(ident, data);
         }
 
-        let mut io_object = IdentMap::default();
+        let mut io_object = ObjectMap::default();
         for (name, func, arg_count) in builtins::io::BUILTINS.into_iter() {
             let ident = state.interner.intern(*name);
             let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
@@ -366,23 +410,167 @@ impl Interpreter {
             io_object.insert(ident, data);
         }
 
+        let mut set_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::set::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            set_object.insert(ident, data);
+        }
+
+        let mut persistent_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::persistent::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            persistent_object.insert(ident, data);
+        }
+
+        let mut seq_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::seq::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            seq_object.insert(ident, data);
+        }
+
+        let mut iter_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::iter::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            iter_object.insert(ident, data);
+        }
+        let done_dr = self.data.insert(Data::Done);
+        done_dr.set_pinned();
+        iter_object.insert(state.interner.intern("done"), done_dr);
+
+        let mut lazy_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::lazy::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            lazy_object.insert(ident, data);
+        }
+
+        let mut list_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::list::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            list_object.insert(ident, data);
+        }
+
+        let mut object_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::object::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            object_object.insert(ident, data);
+        }
+
+        let mut regex_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::regex::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            regex_object.insert(ident, data);
+        }
+
+        let mut math_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::math::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            math_object.insert(ident, data);
+        }
+        for (name, value) in builtins::math::CONSTANTS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::Float(*value));
+            data.set_pinned();
+            data.freeze();
+            math_object.insert(ident, data);
+        }
+
+        let mut array_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::array::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            array_object.insert(ident, data);
+        }
+
+        let mut random_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::random::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            random_object.insert(ident, data);
+        }
+
+        let mut time_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::time::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            time_object.insert(ident, data);
+        }
+
+        let mut fs_object = ObjectMap::default();
+        for (name, func, arg_count) in builtins::fs::BUILTINS.into_iter() {
+            let ident = state.interner.intern(*name);
+            let data = self.data.insert(Data::NativeFn(name, *func, *arg_count));
+            data.set_pinned();
+            fs_object.insert(ident, data);
+        }
+
         let stdout_dr = self.data.insert(Data::NativeData(NativeData::Stdout));
         let stdin = Rc::new(RefCell::new(BufReader::new(stdin())));
         let stdin_dr = self.data.insert(Data::NativeData(NativeData::Stdin(stdin)));
 
+        let stderr_dr = self.data.insert(Data::NativeData(NativeData::Stderr));
+
         stdout_dr.set_pinned();
         stdin_dr.set_pinned();
+        stderr_dr.set_pinned();
         io_object.insert(state.interner.intern("stdout"), stdout_dr);
         io_object.insert(state.interner.intern("stdin"), stdin_dr);
+        io_object.insert(state.interner.intern("stderr"), stderr_dr);
 
         let string_data = self.data.insert(Data::Object(string_object));
         let misc_data = self.data.insert(Data::Object(misc_object));
         let io_data = self.data.insert(Data::Object(io_object));
-
-        let mut std_object = IdentMap::default();
+        let set_data = self.data.insert(Data::Object(set_object));
+        let persistent_data = self.data.insert(Data::Object(persistent_object));
+        let seq_data = self.data.insert(Data::Object(seq_object));
+        let iter_data = self.data.insert(Data::Object(iter_object));
+        let lazy_data = self.data.insert(Data::Object(lazy_object));
+        let list_data = self.data.insert(Data::Object(list_object));
+        let object_data = self.data.insert(Data::Object(object_object));
+        let regex_data = self.data.insert(Data::Object(regex_object));
+        let math_data = self.data.insert(Data::Object(math_object));
+        let array_data = self.data.insert(Data::Object(array_object));
+        let random_data = self.data.insert(Data::Object(random_object));
+        let time_data = self.data.insert(Data::Object(time_object));
+        let fs_data = self.data.insert(Data::Object(fs_object));
+
+        let mut std_object = ObjectMap::default();
         std_object.insert(state.intern("string"), string_data);
         std_object.insert(state.intern("misc"), misc_data);
         std_object.insert(state.intern("io"), io_data);
+        std_object.insert(state.intern("set"), set_data);
+        std_object.insert(state.intern("persistent"), persistent_data);
+        std_object.insert(state.intern("seq"), seq_data);
+        std_object.insert(state.intern("iter"), iter_data);
+        std_object.insert(state.intern("lazy"), lazy_data);
+        std_object.insert(state.intern("list"), list_data);
+        std_object.insert(state.intern("object"), object_data);
+        std_object.insert(state.intern("regex"), regex_data);
+        std_object.insert(state.intern("math"), math_data);
+        std_object.insert(state.intern("array"), array_data);
+        std_object.insert(state.intern("random"), random_data);
+        std_object.insert(state.intern("time"), time_data);
+        std_object.insert(state.intern("fs"), fs_data);
 
         self.root_env.insert(state.intern("std"), self.data.insert(Data::Object(std_object)));
     }
@@ -420,7 +608,6 @@ impl Interpreter {
     }
 
     #[inline]
-    #[allow(dead_code)]
     pub fn pop_env_scope(&mut self) {
         let count = self.env_stack[0].pop_scope();
         self.var_count -= count;
@@ -489,6 +676,31 @@ impl Interpreter {
         bail!("Attempt to access undefined variable: `{}`", interner.get(var));
     }
 
+    /// Gets the variable at the start of the path, then follows the rest of the path through
+    /// object fields
+    pub fn get_path(&self, path: &[Ident], interner: &Interner)->Result<DataRef> {
+        let mut path_iter = path.iter().copied();
+        let mut obj = self.get_var(path_iter.next().unwrap(), interner)?;
+        for name in path_iter {
+            let data = obj.get_data();
+            match &*data {
+                Data::Object(fields)=>{
+                    if let Some(dr) = fields.get(&name) {
+                        let dr = dr.clone();
+                        drop(data);
+
+                        obj = dr;
+                    } else {
+                        bail!("Object does not have a field named {}", interner.get(name));
+                    }
+                },
+                _=>bail!("Paths can only be used on `Object`s"),
+            }
+        }
+
+        return Ok(obj);
+    }
+
     #[inline]
     pub fn alloc(&mut self, data: Data)->DataRef {
         self.metrics.allocations += 1;
@@ -525,7 +737,6 @@ impl Interpreter {
     // TODO: Make `DataStore` aware of the data in `scopes` and `call_stack` before we do a GC and
     // cause a use-after-free bug
     pub fn run(&mut self, state: &mut ConvertState, start_id: Option<InstructionId>)->Result<Option<DataRef>> {
-        use Instruction as I;
         // dbg!(&state.interner);
         // for (i, ins) in state.instructions.iter().enumerate() {
         //     print!("{:>4} ", i);
@@ -546,18 +757,120 @@ impl Interpreter {
         //     }
         // }
 
-        const MAX_ITERS: u64 = 1_000_000_000;
-
         let start = Instant::now();
 
+        self.scopes.push(ScopeItem::Return(None));
+
+        self.run_instructions(state, start_id)?;
+
+        let duration = start.elapsed();
+
+        self.metrics.last_run_time = duration;
+        self.metrics.total_run_time += duration;
+
+        let allocation_bytes = self.data.get_alloc_rem() as u64;
+        self.metrics.max_allocation_bytes = self.metrics.max_allocation_bytes.max(allocation_bytes);
+
+        self.data.collect(&self.call_stack, &self.scopes);
+
+        return Ok(self.pop_from_scope());
+    }
+
+    /// Calls any callable value and runs it to completion. This is how native functions call back
+    /// into the script.
+    ///
+    /// The function and args are rooted while the call runs, but anything else the native function
+    /// is holding on to has to be rooted with [`DataRef::external`] if the script could collect.
+    pub fn call_value(&mut self, func: DataRef, args: Vec<DataRef>, state: &mut ConvertState)->Result<DataRef> {
+        let call_depth = self.call_stack.len();
+        let env_depth = self.env_stack.len();
+
+        let _func_root = func.clone().external();
+        let res = self.call_value_inner(func, args, state);
+
+        // an error leaves the nested frames behind, so unwind back to where we started
+        if res.is_err() {
+            while self.call_stack.len() > call_depth {
+                let (_, ret_scopes) = self.call_stack.pop().unwrap();
+                self.scopes = ret_scopes;
+            }
+            while self.env_stack.len() > env_depth {
+                self.pop_env();
+            }
+        }
+
+        return res;
+    }
+
+    fn call_value_inner(&mut self, func: DataRef, mut args: Vec<DataRef>, state: &mut ConvertState)->Result<DataRef> {
+        let func_ref = func.get_data();
+        match &*func_ref {
+            Data::NativeFn(name, f, arg_count)=>{
+                let (name, f, arg_count) = (*name, *f, *arg_count);
+                drop(func_ref);
+
+                return self.call_native(name, f, arg_count, args, state);
+            },
+            Data::Fn(_)|Data::Closure{..}=>{
+                drop(func_ref);
+
+                // the invalid return id makes `Return` hand the result back to us
+                let body_ptr = self.enter_call(&func, func.clone(), args, InstructionId::invalid(), state)?;
+                match self.run_instructions(state, Some(body_ptr))? {
+                    Some(dr)=>return Ok(dr),
+                    None=>bail!("Function exited without returning"),
+                }
+            },
+            Data::Object(_)=>{
+                let Some(method) = self.get_callable(&func_ref, None)? else {
+                    bail!("Cannot call this object");
+                };
+                drop(func_ref);
+
+                args.insert(0, func);
+                return self.call_value_inner(method, args, state);
+            },
+            Data::Partial{func: inner, args: given}=>{
+                let inner = inner.clone();
+                let mut all_args = given.clone();
+                drop(func_ref);
+
+                all_args.append(&mut args);
+                return self.call_value_inner(inner, all_args, state);
+            },
+            Data::Composed(funcs)=>{
+                let funcs = funcs.clone();
+                drop(func_ref);
+
+                let mut funcs = funcs.into_iter().rev();
+                let Some(first) = funcs.next() else {
+                    bail!("Cannot call an empty composition");
+                };
+
+                let mut res = self.call_value_inner(first, args, state)?;
+                for func in funcs {
+                    res = self.call_value_inner(func, vec![res], state)?;
+                }
+
+                return Ok(res);
+            },
+            data=>bail!("Value is not callable! {:?}", data),
+        }
+    }
+
+    /// Runs until `Exit`, or until a `Return` to an invalid instruction id which means we were
+    /// called from `call_value`. In that case the returned data is given back.
+    fn run_instructions(&mut self, state: &mut ConvertState, start_id: Option<InstructionId>)->Result<Option<DataRef>> {
+        use Instruction as I;
+
+        const MAX_ITERS: u64 = 1_000_000_000;
+
         let mut iter = state.instructions.iter();
 
         if let Some(start_id) = start_id {
             iter.jump(start_id);
         }
 
-        self.scopes.push(ScopeItem::Return(None));
-
         let mut ins_count = 0;
 
         while let Some(ins) = iter.next() {
@@ -574,10 +887,19 @@ impl Interpreter {
 
             match ins {
                 I::Nop=>{},
+                I::Fmt=>self.push_dr_to_scope(self.fmt_fn.clone()),
                 I::Exit=>break,
 
-                I::ReturnModule=>{
-                    let module = self.env_to_object();
+                I::ReturnModule(consts)=>{
+                    let mut module = self.env_to_object();
+                    if !consts.is_empty() {
+                        let names = consts.iter()
+                            .map(|name|self.alloc(Data::Ident(*name)))
+                            .collect();
+                        let names = self.alloc(Data::List(names));
+                        let Data::Object(fields) = &mut module else {unreachable!()};
+                        fields.insert(self.consts_ident, names);
+                    }
                     let (ret_id, ret_scopes) = self.call_stack.pop().unwrap();
 
                     iter.jump(ret_id);
@@ -631,35 +953,100 @@ impl Interpreter {
                 },
 
                 I::Object(fields)=>{
-                    let mut map = IdentMap::default();
-                    for field in fields.iter().rev().copied() {
-                        let data = self.pop_from_scope().unwrap();
-                        map.insert(field, data);
+                    // the values are popped in reverse, so collect them first to keep the fields
+                    // in the order they were written
+                    let mut values = Vec::with_capacity(fields.len());
+                    for _ in 0..fields.len() {
+                        values.push(self.pop_from_scope().unwrap());
+                    }
+
+                    let mut map = ObjectMap::default();
+                    map.reserve(fields.len());
+                    for (field, data) in fields.iter().copied().zip(values.into_iter().rev()) {
+                        match field {
+                            Some(field)=>{
+                                map.insert(field, data);
+                            },
+                            // later fields win, so splats can be overridden and can override. The new
+                            // object is a plain object, so the struct type and module constants are
+                            // left behind.
+                            None=>match &*data.get_data() {
+                                Data::Object(other)=>map.extend(other.iter()
+                                    .filter(|(name, _)|!self.is_marker_field(**name))
+                                    .map(|(name, dr)|(*name, dr.clone()))
+                                ),
+                                _=>bail!("Type error: Only objects can be splatted into an object"),
+                            },
+                        }
                     }
                     self.push_to_scope(Data::Object(map));
                 },
 
                 I::Path(path)=>{
-                    let mut path_iter = path.iter().copied();
-                    let mut obj = self.get_var(path_iter.next().unwrap(), &state.interner)?;
-                    for name in path_iter {
-                        let data = obj.get_data();
-                        match &*data {
-                            Data::Object(fields)=>{
-                                if let Some(dr) = fields.get(&name) {
-                                    let dr = dr.clone();
-                                    drop(data);
+                    let obj = self.get_path(path, &state.interner)?;
 
-                                    obj = dr;
-                                } else {
-                                    bail!("Object does not have a field named {}", state.interner.get(name));
-                                }
-                            },
-                            _=>bail!("Paths can only be used on `Object`s"),
-                        }
+                    self.push_dr_to_scope(obj);
+                },
+                I::SetPath(path)=>{
+                    let data = self.scopes[0].last().unwrap();
+                    let (last, path) = path.split_last().unwrap();
+
+                    let mut obj = self.get_path(path, &state.interner)?;
+                    let mut obj_ref = obj.try_get_data_mut()?;
+                    match &mut *obj_ref {
+                        Data::Object(fields)=>{
+                            self.check_struct_field(fields, *last, &state.interner)?;
+                            self.check_const_field(fields, *last, &state.interner)?;
+                            fields.insert(*last, data);
+                        },
+                        _=>bail!("Paths can only be used on `Object`s"),
                     }
+                },
 
-                    self.push_dr_to_scope(obj);
+                I::Super(name)=>{
+                    let vtable = self.pop_from_scope().unwrap();
+                    let parent = match &*vtable.get_data() {
+                        Data::Object(entries)=>entries.get(&self.vtable_ident).cloned(),
+                        _=>bail!("Vtable is not an object!"),
+                    };
+                    let Some(parent) = parent else {
+                        bail!("Cannot call `super` on a vtable without a parent");
+                    };
+
+                    match self.lookup_method(parent, Some(*name))? {
+                        Some(method)=>self.push_dr_to_scope(method),
+                        None=>bail!("Method `{}` does not exist on the parent vtable", state.interner.get(*name)),
+                    }
+                },
+                I::IsStruct(name)=>{
+                    let data = self.pop_from_scope().unwrap();
+                    let is_struct = match &*data.get_data() {
+                        Data::Object(fields)=>self.struct_type(fields) == Some(*name),
+                        _=>false,
+                    };
+
+                    self.push_to_scope(Data::Bool(is_struct));
+                },
+                I::StructField{name, field}=>{
+                    let data = self.pop_from_scope().unwrap();
+                    let data_ref = data.get_data();
+                    let field_data = match &*data_ref {
+                        Data::Object(fields) if self.struct_type(fields) == Some(*name)=>{
+                            fields.get(field).cloned()
+                        },
+                        _=>None,
+                    };
+                    drop(data_ref);
+
+                    match field_data {
+                        Some(dr)=>self.push_dr_to_scope(dr),
+                        None=>bail!(
+                            "Type error: `{}-{}` only accepts `{}` structs",
+                            state.interner.get(*name),
+                            state.interner.get(*field),
+                            state.interner.get(*name),
+                        ),
+                    }
                 },
 
                 I::DotIdent(i)=>self.push_to_scope(Data::Ident(*i)),
@@ -667,6 +1054,7 @@ impl Interpreter {
                 I::Float(f)=>self.push_to_scope(Data::Float(*f)),
                 I::String(s)=>self.push_to_scope(Data::String(s.clone())),
                 I::Char(c)=>self.push_to_scope(Data::Char(*c)),
+                I::Byte(b)=>self.push_to_scope(Data::Byte(*b)),
                 I::True=>self.push_to_scope(Data::Bool(true)),
                 I::False=>self.push_to_scope(Data::Bool(false)),
 
@@ -722,8 +1110,10 @@ impl Interpreter {
                                             drop(data);
 
                                             let data = args[2].clone();
-                                            let mut dr_ref = args[0].get_data_mut();
+                                            let mut dr_ref = args[0].try_get_data_mut()?;
                                             let Data::Object(fields) = &mut *dr_ref else {unreachable!()};
+                                            self.check_struct_field(fields, name, &state.interner)?;
+                                            self.check_const_field(fields, name, &state.interner)?;
 
                                             fields.insert(name, data.clone());
 
@@ -745,75 +1135,39 @@ impl Interpreter {
 
                         match &*data {
                             Data::NativeFn(name, f, arg_count)=>{
-                                let dr = match arg_count {
-                                    ArgCount::Exact(count)=>if args.len() == *count {
-                                        f(args, self, &mut state.interner)?
-                                    } else {
-                                        bail!("Function `{name}` cannot take {} arguments", args.len());
-                                    },
-                                    ArgCount::Any=>f(args, self, &mut state.interner)?,
-                                };
-                                self.push_dr_to_scope(dr);
-                            },
-                            Data::Fn(id)=>{
-                                self.debug_call(*id, state);
-
-                                let func = state.fns.get(*id).unwrap();
+                                let (name, f, arg_count) = (*name, *f, *arg_count);
+                                drop(data);
 
+                                // native functions can call back into the script, so they get the
+                                // whole state and we pick up where we were afterwards
                                 let next_ins_id = iter.next_ins_id().unwrap();
-                                let old_scopes = replace(&mut self.scopes, Stack::new());
-                                self.call_stack.push((next_ins_id, old_scopes));
-                                self.scopes.push(ScopeItem::Return(None));
-                                self.push_env();
-                                self.push_env_scope();
-
-                                if let Some((params, body_ptr)) = func.sig.match_arg_count(args.len()) {
-                                    self.set_func_args(arg0_func, params, args, &state.interner)?;
+                                drop(iter);
 
-                                    iter.jump(body_ptr);
-                                } else {
-                                    if let Some(name) = func.name {
-                                        bail!("Function `{}` cannot take {} arguments", state.interner.get(name), args.len());
-                                    } else {
-                                        bail!("Function with ID `{:?}` cannot take {} arguments", id, args.len());
-                                    }
-                                }
+                                let dr = self.call_native(name, f, arg_count, args, state)?;
 
-                                self.metrics.max_call_stack_depth = self.metrics.max_call_stack_depth
-                                    .max(self.call_stack.len() as u16);
+                                iter = state.instructions.iter();
+                                iter.jump(next_ins_id);
+                                self.push_dr_to_scope(dr);
                             },
-                            Data::Closure{id, captures}=>{
-                                self.debug_call(*id, state);
-
-                                let func = state.fns.get(*id).unwrap();
+                            Data::Partial{..}|Data::Composed(_)=>{
+                                drop(data);
 
                                 let next_ins_id = iter.next_ins_id().unwrap();
-                                let old_scopes = replace(&mut self.scopes, Stack::new());
-                                self.call_stack.push((next_ins_id, old_scopes));
-                                self.scopes.push(ScopeItem::Return(None));
-                                self.push_env();
-                                self.push_env_scope();
+                                drop(iter);
 
-                                for (name, data) in &captures.0 {
-                                    self.define_var(*name, data.clone(), &state.interner)?;
-                                }
-
-                                self.push_env_scope();
+                                let dr = self.call_value(arg0.clone(), args, state)?;
 
-                                if let Some((params, body_ptr)) = func.sig.match_arg_count(args.len()) {
-                                    self.set_func_args(arg0_func, params, args, &state.interner)?;
+                                iter = state.instructions.iter();
+                                iter.jump(next_ins_id);
+                                self.push_dr_to_scope(dr);
+                            },
+                            Data::Fn(_)|Data::Closure{..}=>{
+                                drop(data);
 
-                                    iter.jump(body_ptr);
-                                } else {
-                                    if let Some(name) = func.name {
-                                        bail!("Function `{}` cannot take {} arguments", state.interner.get(name), args.len());
-                                    } else {
-                                        bail!("Function with ID `{:?}` cannot take {} arguments", id, args.len());
-                                    }
-                                }
+                                let next_ins_id = iter.next_ins_id().unwrap();
+                                let body_ptr = self.enter_call(&arg0, arg0_func, args, next_ins_id, state)?;
 
-                                self.metrics.max_call_stack_depth = self.metrics.max_call_stack_depth
-                                    .max(self.call_stack.len() as u16);
+                                iter.jump(body_ptr);
                             },
                             arg=>bail!("Arg0 is not callable! {:?}", arg),
                         }
@@ -860,14 +1214,30 @@ impl Interpreter {
 
                         match &*data {
                             Data::NativeFn(name, f, arg_count)=>{
-                                let dr = match arg_count {
-                                    ArgCount::Exact(count)=>if args.len() == *count {
-                                        f(args, self, &mut state.interner)?
-                                    } else {
-                                        bail!("Function `{name}` cannot take {} arguments", args.len());
-                                    },
-                                    ArgCount::Any=>f(args, self, &mut state.interner)?,
-                                };
+                                let (name, f, arg_count) = (*name, *f, *arg_count);
+                                drop(data);
+
+                                // native functions can call back into the script, so they get the
+                                // whole state and we pick up where we were afterwards
+                                let next_ins_id = iter.next_ins_id().unwrap();
+                                drop(iter);
+
+                                let dr = self.call_native(name, f, arg_count, args, state)?;
+
+                                iter = state.instructions.iter();
+                                iter.jump(next_ins_id);
+                                self.push_dr_to_scope(dr);
+                            },
+                            Data::Partial{..}|Data::Composed(_)=>{
+                                drop(data);
+
+                                let next_ins_id = iter.next_ins_id().unwrap();
+                                drop(iter);
+
+                                let dr = self.call_value(arg0.clone(), args, state)?;
+
+                                iter = state.instructions.iter();
+                                iter.jump(next_ins_id);
                                 self.push_dr_to_scope(dr);
                             },
                             Data::Fn(id)=>{
@@ -933,9 +1303,13 @@ impl Interpreter {
                     let (ret_id, ret_scopes) = self.call_stack.pop().unwrap();
 
                     self.pop_env();
+                    self.scopes = ret_scopes;
+
+                    if !ret_id.is_valid() {
+                        return Ok(Some(last));
+                    }
 
                     iter.jump(ret_id);
-                    self.scopes = ret_scopes;
                     self.push_dr_to_scope(last);
                 },
 
@@ -969,7 +1343,39 @@ impl Interpreter {
                     if self.env_stack.len() == 0 {
                         self.var_count -= self.root_env.pop_scope();
                     } else {
-                        self.push_env_scope();
+                        self.pop_env_scope();
+                    }
+                },
+
+                I::IterStart=>{
+                    let data = self.pop_from_scope().unwrap();
+
+                    // `$iter` can call back into the script
+                    let next_ins_id = iter.next_ins_id().unwrap();
+                    drop(iter);
+
+                    let it = self.make_iter(&data, state)?;
+
+                    iter = state.instructions.iter();
+                    iter.jump(next_ins_id);
+                    self.push_dr_to_scope(it);
+                },
+                I::IterNext(end_id)=>{
+                    let end_id = *end_id;
+                    let it = self.pop_from_scope().unwrap();
+
+                    let next_ins_id = iter.next_ins_id().unwrap();
+                    drop(iter);
+
+                    let item = self.iter_next(&it, state)?;
+
+                    iter = state.instructions.iter();
+                    match item {
+                        Some(item)=>{
+                            iter.jump(next_ins_id);
+                            self.push_dr_to_scope(item);
+                        },
+                        None=>iter.jump(end_id),
                     }
                 },
 
@@ -994,17 +1400,69 @@ impl Interpreter {
             }
         }
 
-        let duration = start.elapsed();
+        return Ok(None);
+    }
 
-        self.metrics.last_run_time = duration;
-        self.metrics.total_run_time += duration;
+    fn call_native(&mut self, name: &str, f: NativeFn, arg_count: ArgCount, args: Vec<DataRef>, state: &mut ConvertState)->Result<DataRef> {
+        match arg_count {
+            ArgCount::Exact(count)=>if args.len() != count {
+                bail!("Function `{name}` cannot take {} arguments", args.len());
+            },
+            ArgCount::Any=>{},
+        }
 
-        let allocation_bytes = self.data.get_alloc_rem() as u64;
-        self.metrics.max_allocation_bytes = self.metrics.max_allocation_bytes.max(allocation_bytes);
+        // the args were popped off the scope, so keep them alive in case the native calls back into
+        // the script and that collects
+        let _roots = args.iter()
+            .cloned()
+            .map(DataRef::external)
+            .collect::<Vec<_>>();
 
-        self.data.collect(&self.call_stack, &self.scopes);
+        return f(args, self, state);
+    }
 
-        return Ok(self.pop_from_scope());
+    /// Pushes a call frame for a `Fn` or `Closure` and sets its arguments. Returns where the body
+    /// starts. `recur` is what the function sees as `recur`; for methods that is the object.
+    fn enter_call(&mut self, callee: &DataRef, recur: DataRef, args: Vec<DataRef>, ret_id: InstructionId, state: &ConvertState)->Result<InstructionId> {
+        let callee_ref = callee.get_data();
+        let (id, captures) = match &*callee_ref {
+            Data::Fn(id)=>(*id, None),
+            Data::Closure{id, captures}=>(*id, Some(captures)),
+            arg=>bail!("Arg0 is not callable! {:?}", arg),
+        };
+
+        self.debug_call(id, state);
+
+        let func = state.fns.get(id).unwrap();
+
+        let old_scopes = replace(&mut self.scopes, Stack::new());
+        self.call_stack.push((ret_id, old_scopes));
+        self.scopes.push(ScopeItem::Return(None));
+        self.push_env();
+        self.push_env_scope();
+
+        if let Some(captures) = captures {
+            for (name, data) in &captures.0 {
+                self.define_var(*name, data.clone(), &state.interner)?;
+            }
+
+            self.push_env_scope();
+        }
+
+        let Some((params, body_ptr)) = func.sig.match_arg_count(args.len()) else {
+            if let Some(name) = func.name {
+                bail!("Function `{}` cannot take {} arguments", state.interner.get(name), args.len());
+            } else {
+                bail!("Function with ID `{:?}` cannot take {} arguments", id, args.len());
+            }
+        };
+
+        self.set_func_args(recur, params, args, &state.interner)?;
+
+        self.metrics.max_call_stack_depth = self.metrics.max_call_stack_depth
+            .max(self.call_stack.len() as u16);
+
+        return Ok(body_ptr);
     }
 
     fn env_to_object(&mut self)->Data {
@@ -1038,26 +1496,445 @@ impl Interpreter {
         return Ok(());
     }
 
+    /// Gets a method from the vtable if the data is an object with one
+    pub fn get_method(&self, dr: &DataRef, name: Ident)->Result<Option<DataRef>> {
+        match &*dr.get_data() {
+            Data::Object(fields)=>match fields.get(&self.vtable_ident) {
+                Some(vtable)=>self.lookup_method(vtable.clone(), Some(name)),
+                None=>Ok(None),
+            },
+            _=>Ok(None),
+        }
+    }
+
+    /// Gets an iterator for the data. Iterators are given back as they are, objects with `$iter`
+    /// make their own, and functions are called for each item until they return `done`.
+    pub fn make_iter(&mut self, dr: &DataRef, state: &mut ConvertState)->Result<DataRef> {
+        if self.get_method(dr, self.protocol.next)?.is_some() {
+            return Ok(dr.clone());
+        }
+
+        if let Some(method) = self.get_method(dr, self.protocol.iter)? {
+            let it = self.call_value(method, vec![dr.clone()], state)?;
+            let is_iter = matches!(&*it.get_data(), Data::Iterator(_)) ||
+                self.get_method(&it, self.protocol.next)?.is_some();
+            if !is_iter {
+                bail!("Type error: `$iter` must return an iterator");
+            }
+
+            return Ok(it);
+        }
+
+        let data = dr.get_data();
+        let native = match &*data {
+            Data::Iterator(_)=>return Ok(dr.clone()),
+            Data::LazySeq(_)=>NativeIter::Seq {
+                seq: dr.clone(),
+                idx: 0,
+            },
+            Data::List(_)=>NativeIter::List {
+                list: dr.clone(),
+                idx: 0,
+            },
+            Data::TypedArray(_)=>NativeIter::Array {
+                array: dr.clone(),
+                idx: 0,
+            },
+            Data::String(s)=>NativeIter::Chars {
+                chars: s.chars().collect(),
+                idx: 0,
+            },
+            Data::Set(items)=>NativeIter::Items {
+                items: items.values().cloned().collect(),
+                idx: 0,
+            },
+            Data::PVec(items)=>NativeIter::Items {
+                items: items.to_vec(),
+                idx: 0,
+            },
+            // maps and objects give `(key value)` lists
+            Data::PMap(map)=>{
+                let mut pairs = Vec::with_capacity(map.len());
+                map.for_each(|_, (key, value)|pairs.push((key.clone(), value.clone())));
+                drop(data);
+
+                let items = pairs.into_iter()
+                    .map(|(key, value)|self.alloc(Data::List(vec![key, value])))
+                    .collect();
+                NativeIter::Items {items, idx: 0}
+            },
+            Data::Object(fields)=>{
+                let pairs = fields.iter()
+                    .filter(|(name, _)|**name != self.vtable_ident && **name != self.type_ident && **name != self.consts_ident)
+                    .map(|(name, value)|(*name, value.clone()))
+                    .collect::<Vec<_>>();
+                drop(data);
+
+                let items = pairs.into_iter()
+                    .map(|(name, value)|{
+                        let key = self.alloc(Data::Ident(name));
+                        self.alloc(Data::List(vec![key, value]))
+                    })
+                    .collect();
+                NativeIter::Items {items, idx: 0}
+            },
+            Data::Fn(_)|
+                Data::Closure{..}|
+                Data::NativeFn(..)|
+                Data::Partial{..}|
+                Data::Composed(_)=>NativeIter::Func(dr.clone()),
+            _=>bail!("Type error: Cannot iterate over this value"),
+        };
+
+        return Ok(self.alloc(Data::Iterator(native)));
+    }
+
+    /// Advances the iterator. `None` means it is done.
+    pub fn iter_next(&mut self, it: &DataRef, state: &mut ConvertState)->Result<Option<DataRef>> {
+        if let Some(method) = self.get_method(it, self.protocol.next)? {
+            let item = self.call_value(method, vec![it.clone()], state)?;
+            return Ok(self.not_done(item));
+        }
+
+        let mut it = it.clone();
+        let mut it_ref = it.get_data_mut();
+        let Data::Iterator(native) = &mut *it_ref else {
+            bail!("Type error: Not an iterator");
+        };
+
+        match native {
+            NativeIter::List{list, idx}=>{
+                let item = match &*list.get_data() {
+                    Data::List(items)=>items.get(*idx).cloned(),
+                    _=>None,
+                };
+                *idx += 1;
+
+                return Ok(item);
+            },
+            NativeIter::Array{array, idx}=>{
+                let element = match &*array.get_data() {
+                    Data::TypedArray(arr)=>arr.get(*idx),
+                    _=>None,
+                };
+                *idx += 1;
+                drop(it_ref);
+
+                return Ok(element.map(|element|self.alloc(element.into())));
+            },
+            NativeIter::Chars{chars, idx}=>{
+                let Some(c) = chars.get(*idx).copied() else {return Ok(None)};
+                *idx += 1;
+                drop(it_ref);
+
+                return Ok(Some(self.alloc(Data::Char(c))));
+            },
+            NativeIter::Items{items, idx}=>{
+                let item = items.get(*idx).cloned();
+                *idx += 1;
+
+                return Ok(item);
+            },
+            NativeIter::Range{next, end, step}=>{
+                let Some(n) = *next else {
+                    return Ok(None);
+                };
+                let done = match end {
+                    Some(end)=>if *step > 0 {n >= *end} else {n <= *end},
+                    None=>false,
+                };
+                if done {
+                    return Ok(None);
+                }
+                *next = n.checked_add(*step);
+                drop(it_ref);
+
+                return Ok(Some(self.alloc(Data::Number(n))));
+            },
+            NativeIter::Func(func)=>{
+                let func = func.clone();
+                drop(it_ref);
+
+                let item = self.call_value(func, Vec::new(), state)?;
+                return Ok(self.not_done(item));
+            },
+            NativeIter::Seq{seq, idx}=>{
+                let (seq, at) = (seq.clone(), *idx);
+                *idx += 1;
+                drop(it_ref);
+
+                return self.lazy_get(&seq, at, state);
+            },
+            NativeIter::Iterate{func, cur, started}=>{
+                if !*started {
+                    *started = true;
+                    return Ok(Some(cur.clone()));
+                }
+
+                let (func, prev) = (func.clone(), cur.clone());
+                drop(it_ref);
+
+                let next = self.call_value(func, vec![prev], state)?;
+                if let Data::Iterator(NativeIter::Iterate{cur,..}) = &mut *it.get_data_mut() {
+                    *cur = next.clone();
+                }
+
+                return Ok(Some(next));
+            },
+            NativeIter::Repeat{item, remaining}=>{
+                match remaining {
+                    Some(0)=>return Ok(None),
+                    Some(n)=>*n -= 1,
+                    None=>{},
+                }
+
+                return Ok(Some(item.clone()));
+            },
+            NativeIter::Take{inner, remaining}=>{
+                if *remaining == 0 {
+                    return Ok(None);
+                }
+                *remaining -= 1;
+                let inner = inner.clone();
+                drop(it_ref);
+
+                return self.iter_next(&inner, state);
+            },
+            NativeIter::Skip{inner, count}=>{
+                let (inner, count) = (inner.clone(), replace(count, 0));
+                drop(it_ref);
+
+                for _ in 0..count {
+                    if self.iter_next(&inner, state)?.is_none() {
+                        return Ok(None);
+                    }
+                }
+
+                return self.iter_next(&inner, state);
+            },
+            NativeIter::TakeWhile{inner, pred, done}=>{
+                if *done {
+                    return Ok(None);
+                }
+                let (inner, pred) = (inner.clone(), pred.clone());
+                drop(it_ref);
+
+                let Some(item) = self.iter_next(&inner, state)? else {return Ok(None)};
+                let res = self.call_value(pred, vec![item.clone()], state)?;
+                if let Data::Bool(false) = &*res.get_data() {
+                    if let Data::Iterator(NativeIter::TakeWhile{done,..}) = &mut *it.get_data_mut() {
+                        *done = true;
+                    }
+
+                    return Ok(None);
+                }
+
+                return Ok(Some(item));
+            },
+            NativeIter::Map{inner, func}=>{
+                let (inner, func) = (inner.clone(), func.clone());
+                drop(it_ref);
+
+                let Some(item) = self.iter_next(&inner, state)? else {return Ok(None)};
+                let res = self.call_value(func, vec![item], state)?;
+
+                return Ok(Some(res));
+            },
+            NativeIter::Filter{inner, pred}=>{
+                let (inner, pred) = (inner.clone(), pred.clone());
+                drop(it_ref);
+
+                while let Some(item) = self.iter_next(&inner, state)? {
+                    let item = item.external();
+                    let res = self.call_value(pred.clone(), vec![(*item).clone()], state)?;
+                    if !matches!(&*res.get_data(), Data::Bool(false)) {
+                        return Ok(Some(item.inner()));
+                    }
+                }
+
+                return Ok(None);
+            },
+        }
+    }
+
+    /// Gets an item from a lazy sequence, realizing everything up to it first
+    pub fn lazy_get(&mut self, seq: &DataRef, mut idx: usize, state: &mut ConvertState)->Result<Option<DataRef>> {
+        let mut seq = seq.clone();
+        loop {
+            // the source can read this same sequence, so nothing stays borrowed while it runs
+            let seq_ref = seq.get_data();
+            let Data::LazySeq(lazy) = &*seq_ref else {
+                bail!("Type error: Not a lazy sequence");
+            };
+            if let Some(item) = lazy.cache.get(idx) {
+                return Ok(Some(item.clone()));
+            }
+            let source = lazy.source.clone();
+            drop(seq_ref);
+
+            match source {
+                LazySource::Thunk(thunk)=>{
+                    let coll = self.call_value(thunk, Vec::new(), state)?;
+                    let it = self.make_iter(&coll, state)?;
+
+                    let mut seq_ref = seq.get_data_mut();
+                    let Data::LazySeq(lazy) = &mut *seq_ref else {unreachable!()};
+                    if let LazySource::Thunk(_) = &lazy.source {
+                        lazy.source = LazySource::Iter(it);
+                    }
+                },
+                LazySource::Iter(it)=>{
+                    let item = self.iter_next(&it, state)?;
+
+                    let mut seq_ref = seq.get_data_mut();
+                    let Data::LazySeq(lazy) = &mut *seq_ref else {unreachable!()};
+                    match item {
+                        Some(item)=>lazy.cache.push(item),
+                        None=>lazy.source = LazySource::Done,
+                    }
+                },
+                LazySource::Slice{seq: inner, start, len}=>{
+                    if len.is_some_and(|len|idx >= len) {
+                        return Ok(None);
+                    }
+                    let Some(inner_idx) = start.checked_add(idx) else {
+                        return Ok(None);
+                    };
+
+                    seq = inner;
+                    idx = inner_idx;
+                },
+                LazySource::Done=>return Ok(None),
+            }
+        }
+    }
+
+    fn not_done(&self, item: DataRef)->Option<DataRef> {
+        let is_done = matches!(&*item.get_data(), Data::Done);
+        if is_done {
+            return None;
+        }
+
+        return Some(item);
+    }
+
+    /// Gets the key used by sets and maps. Objects can customize it with a `$hash` method that
+    /// returns a hashable value.
+    pub fn set_key(&mut self, dr: &DataRef, state: &mut ConvertState)->Result<SetKey> {
+        match self.get_method(dr, self.protocol.hash)? {
+            Some(method)=>{
+                let key = self.call_value(method, vec![dr.clone()], state)?;
+                return SetKey::from_data(&key.get_data());
+            },
+            None=>SetKey::from_data(&dr.get_data()),
+        }
+    }
+
+    /// Returns the name of the struct if the object is a struct instance
+    fn struct_type(&self, fields: &ObjectMap)->Option<Ident> {
+        match &*fields.get(&self.type_ident)?.get_data() {
+            Data::Ident(name)=>Some(*name),
+            _=>None,
+        }
+    }
+
+    /// Struct instances can only set the fields they were created with, and their vtable. Nothing
+    /// can set `$type`, so objects can't pretend to be structs.
+    fn check_struct_field(&self, fields: &ObjectMap, name: Ident, interner: &Interner)->Result<()> {
+        if name == self.type_ident {
+            bail!("Cannot set the `$type` of an object");
+        }
+        if name == self.vtable_ident || fields.contains_key(&name) {
+            return Ok(());
+        }
+
+        if let Some(type_name) = self.struct_type(fields) {
+            bail!("Struct `{}` does not have a field named `{}`", interner.get(type_name), interner.get(name));
+        }
+
+        return Ok(());
+    }
+
+    /// Struct instances keep the fields they were created with, so only their vtable and fields they
+    /// don't have can be removed
+    fn check_remove_field(&self, fields: &ObjectMap, name: Ident, interner: &Interner)->Result<()> {
+        if name == self.type_ident {
+            bail!("Cannot remove the `$type` of an object");
+        }
+        if let Some(type_name) = self.struct_type(fields) {
+            if name != self.vtable_ident && fields.contains_key(&name) {
+                bail!("Cannot remove the field `{}` from struct `{}`", interner.get(name), interner.get(type_name));
+            }
+        }
+
+        return self.check_const_field(fields, name, interner);
+    }
+
+    /// `$type` and `$consts` make an object a struct or a module. Copying the fields into a new
+    /// object leaves them behind, so the copy is a plain object.
+    fn is_marker_field(&self, name: Ident)->bool {
+        name == self.type_ident || name == self.consts_ident
+    }
+
+    /// Modules list their constants in `$consts`, and those fields can't be set
+    fn check_const_field(&self, fields: &ObjectMap, name: Ident, interner: &Interner)->Result<()> {
+        let Some(consts) = fields.get(&self.consts_ident) else {
+            return Ok(());
+        };
+        if name == self.consts_ident {
+            bail!("Cannot set the `$consts` of a module");
+        }
+        if let Data::List(names) = &*consts.get_data() {
+            if names.iter().any(|n|*n.get_data() == Data::Ident(name)) {
+                bail!("Cannot assign to the constant `{}`", interner.get(name));
+            }
+        }
+
+        return Ok(());
+    }
+
     fn get_callable(&self, object: &Data, name: Option<Ident>)->Result<Option<DataRef>> {
         match object {
             Data::Object(fields)=>{
                 let Some(vtable) = fields.get(&self.vtable_ident) else {return Ok(None)};
-                let vtable_ref = vtable.get_data();
-                match &*vtable_ref {
-                    Data::Object(entries)=>{
-                        if let Some(name) = name {
-                            return Ok(entries.get(&name).cloned());
-                        } else {
-                            return Ok(entries.get(&self.vtable_ident).cloned());
-                        }
-                    },
-                    _=>bail!("Vtable is not an object!"),
-                }
+                return self.lookup_method(vtable.clone(), name);
             },
             _=>bail!("Not an object"),
         }
     }
 
+    /// Finds a method in the vtable or its parents. The `$` entry of a vtable is its parent if it
+    /// is an object, otherwise it is the function used to call the object directly.
+    fn lookup_method(&self, mut vtable: DataRef, name: Option<Ident>)->Result<Option<DataRef>> {
+        for _ in 0..MAX_VTABLE_DEPTH {
+            let vtable_ref = vtable.get_data();
+            let Data::Object(entries) = &*vtable_ref else {bail!("Vtable is not an object!")};
+
+            if let Some(name) = name {
+                if let Some(method) = entries.get(&name) {
+                    return Ok(Some(method.clone()));
+                }
+            }
+
+            let Some(parent) = entries.get(&self.vtable_ident).cloned() else {return Ok(None)};
+            drop(vtable_ref);
+
+            match &*parent.get_data() {
+                Data::Object(_)=>{},
+                // the call function can only be used when calling the object directly
+                _=>if name.is_none() {
+                    return Ok(Some(parent.clone()));
+                } else {
+                    return Ok(None);
+                },
+            }
+
+            vtable = parent;
+        }
+
+        bail!("Vtable parent chain is longer than {MAX_VTABLE_DEPTH}. Is there a cycle?");
+    }
+
     fn debug_call(&self, id: FnId, state: &ConvertState) {
         if !DEBUG {return}
 
@@ -1092,3 +1969,65 @@ impl Interpreter {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the source and gives the result, which has to be a Bool
+    fn run_bool(source: &str)->Result<bool> {
+        let exprs = crate::parser::new_parser(source).parse_all()?;
+        let mut state = convert(exprs)?;
+        let mut interpreter = Interpreter::new(&mut state);
+        let Some(res) = interpreter.run(&mut state, None)? else {
+            bail!("Nothing was returned");
+        };
+
+        match &*res.get_data() {
+            Data::Bool(b)=>Ok(*b),
+            data=>bail!("Expected a Bool, got {data:?}"),
+        }
+    }
+
+    #[test]
+    fn struct_predicate() {
+        assert!(run_bool("(defstruct Person [name]) (Person? (Person \"a\"))").unwrap());
+        assert!(!run_bool("(defstruct Person [name]) (Person? (object (.name \"a\")))").unwrap());
+    }
+
+    #[test]
+    fn splat_does_not_copy_struct_type() {
+        assert!(!run_bool("(defstruct Person [name]) (Person? (object ...(Person \"a\")))").unwrap());
+        assert!(!run_bool("(defstruct Person [name]) (Person? (object ...(Person \"a\") (.extra 1)))").unwrap());
+    }
+
+    #[test]
+    fn object_literals_cannot_set_type() {
+        assert!(run_bool("(defstruct Person [name]) (Person? (object (.$type .Person) (.name \"a\")))").is_err());
+        assert!(run_bool("(defstruct Person [name]) (def $type .Person) (Person? (object .$type .name))").is_err());
+    }
+
+    #[test]
+    fn object_builtins_make_plain_objects() {
+        assert!(!run_bool("(defstruct Person [name]) (Person? (std/object/merge (Person \"a\")))").unwrap());
+        assert!(!run_bool("(defstruct Person [name]) (Person? (std/object/fromEntries (std/object/entries (Person \"a\"))))").unwrap());
+        assert!(!run_bool("(defstruct Person [name]) (Person? (std/object/fromEntries (core/list (core/list \"$type\" .Person) (core/list .name 1))))").unwrap());
+    }
+
+    #[test]
+    fn remove_keeps_struct_fields() {
+        assert!(run_bool("(defstruct Person [name]) (std/object/remove (Person \"a\") .name)").is_err());
+        assert!(run_bool("(defstruct Person [name]) (std/object/remove (Person \"a\") \"$type\")").is_err());
+        assert!(!run_bool("(def o (object (.a 1))) (std/object/remove o .a) (std/object/has o .a)").unwrap());
+    }
+
+    #[test]
+    fn open_rejects_conflicting_modes() {
+        let err = run_bool("(std/io/open \"unused.txt\" .write .append)").unwrap_err();
+        assert!(err.to_string().contains("`.write` and `.append`"), "{err}");
+
+        let err = run_bool("(std/io/open \"unused.txt\" .read .append)").unwrap_err();
+        assert!(err.to_string().contains("reading and writing"), "{err}");
+    }
+}
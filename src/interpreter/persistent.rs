@@ -0,0 +1,531 @@
+//! Persistent (immutable) collections with structural sharing. Every "modifying" operation returns
+//! a new collection and leaves the old one untouched, but only the path to the changed element is
+//! copied. Everything else is shared through `Rc`s, so cloning is cheap and handing one of these to
+//! a callback never needs a defensive copy.
+//!
+//! `PVec` is a 32-way trie with a tail buffer, like Clojure's `PersistentVector`. `PMap` is a hash
+//! array mapped trie (HAMT) with hash collisions stored in the leaves.
+
+
+use rustc_hash::FxHasher;
+use std::{
+    fmt::{
+        Debug,
+        Formatter,
+        Result as FmtResult,
+    },
+    hash::{
+        Hasher,
+        Hash,
+    },
+    rc::Rc,
+};
+
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+
+enum VecNode<T> {
+    Branch(Vec<Rc<VecNode<T>>>),
+    Leaf(Vec<T>),
+}
+impl<T> VecNode<T> {
+    fn children(&self)->&Vec<Rc<VecNode<T>>> {
+        match self {
+            Self::Branch(children)=>children,
+            Self::Leaf(_)=>unreachable!("Expected a branch node"),
+        }
+    }
+}
+
+pub struct PVec<T> {
+    len: usize,
+    /// How far we have to shift an index to get the slot in the root
+    shift: usize,
+    root: Rc<VecNode<T>>,
+    /// The last (up to) `WIDTH` items are kept out of the tree so pushing is cheap
+    tail: Rc<Vec<T>>,
+}
+impl<T> Clone for PVec<T> {
+    fn clone(&self)->Self {
+        PVec {
+            len: self.len,
+            shift: self.shift,
+            root: self.root.clone(),
+            tail: self.tail.clone(),
+        }
+    }
+}
+impl<T: Debug> Debug for PVec<T> {
+    fn fmt(&self, f: &mut Formatter)->FmtResult {
+        let mut list = f.debug_list();
+        self.for_each(|item|{list.entry(item);});
+        list.finish()
+    }
+}
+impl<T: PartialEq> PartialEq for PVec<T> {
+    fn eq(&self, other: &Self)->bool {
+        if self.len != other.len {return false}
+
+        (0..self.len).all(|i|self.get(i) == other.get(i))
+    }
+}
+impl<T: Clone> FromIterator<T> for PVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I)->Self {
+        let mut out = PVec::new();
+        for item in iter {
+            out = out.push(item);
+        }
+
+        return out;
+    }
+}
+impl<T> PVec<T> {
+    pub fn new()->Self {
+        PVec {
+            len: 0,
+            shift: BITS,
+            root: Rc::new(VecNode::Branch(Vec::new())),
+            tail: Rc::new(Vec::new()),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self)->usize {
+        self.len
+    }
+
+    fn tail_offset(&self)->usize {
+        if self.len < WIDTH {
+            0
+        } else {
+            ((self.len - 1) >> BITS) << BITS
+        }
+    }
+
+    pub fn get(&self, idx: usize)->Option<&T> {
+        if idx >= self.len {return None}
+
+        let tail_offset = self.tail_offset();
+        if idx >= tail_offset {
+            return self.tail.get(idx - tail_offset);
+        }
+
+        let mut node = &self.root;
+        let mut level = self.shift;
+        while level > 0 {
+            node = &node.children()[(idx >> level) & MASK];
+            level -= BITS;
+        }
+
+        match &**node {
+            VecNode::Leaf(items)=>items.get(idx & MASK),
+            VecNode::Branch(_)=>unreachable!("Expected a leaf node"),
+        }
+    }
+
+    /// Visit every item in order
+    pub fn for_each<F: FnMut(&T)>(&self, mut f: F) {
+        fn visit<T, F: FnMut(&T)>(node: &VecNode<T>, f: &mut F) {
+            match node {
+                VecNode::Branch(children)=>children.iter().for_each(|c|visit(c, f)),
+                VecNode::Leaf(items)=>items.iter().for_each(|i|f(i)),
+            }
+        }
+
+        visit(&self.root, &mut f);
+        self.tail.iter().for_each(f);
+    }
+}
+impl<T: Clone> PVec<T> {
+    pub fn to_vec(&self)->Vec<T> {
+        let mut out = Vec::with_capacity(self.len);
+        self.for_each(|item|out.push(item.clone()));
+
+        return out;
+    }
+
+    /// Returns a new vector with `item` on the end
+    pub fn push(&self, item: T)->Self {
+        // there is still room in the tail
+        if self.len - self.tail_offset() < WIDTH {
+            let mut tail = (*self.tail).clone();
+            tail.push(item);
+
+            return PVec {
+                len: self.len + 1,
+                shift: self.shift,
+                root: self.root.clone(),
+                tail: Rc::new(tail),
+            };
+        }
+
+        // the tail is full, so move it into the tree
+        let tail_node = Rc::new(VecNode::Leaf((*self.tail).clone()));
+        let mut shift = self.shift;
+        let root;
+
+        if (self.len >> BITS) > (1 << self.shift) {
+            // the root is full, so the tree gets one level deeper
+            root = Rc::new(VecNode::Branch(vec![
+                self.root.clone(),
+                Self::new_path(self.shift, tail_node),
+            ]));
+            shift += BITS;
+        } else {
+            root = self.push_tail(self.shift, &self.root, tail_node);
+        }
+
+        return PVec {
+            len: self.len + 1,
+            shift,
+            root,
+            tail: Rc::new(vec![item]),
+        };
+    }
+
+    fn push_tail(&self, level: usize, parent: &Rc<VecNode<T>>, tail_node: Rc<VecNode<T>>)->Rc<VecNode<T>> {
+        let sub_idx = ((self.len - 1) >> level) & MASK;
+        let mut children = parent.children().clone();
+
+        if level == BITS {
+            children.push(tail_node);
+        } else if let Some(child) = children.get(sub_idx) {
+            let new_child = self.push_tail(level - BITS, child, tail_node);
+            children[sub_idx] = new_child;
+        } else {
+            children.push(Self::new_path(level - BITS, tail_node));
+        }
+
+        return Rc::new(VecNode::Branch(children));
+    }
+
+    fn new_path(level: usize, node: Rc<VecNode<T>>)->Rc<VecNode<T>> {
+        if level == 0 {
+            return node;
+        }
+
+        Rc::new(VecNode::Branch(vec![Self::new_path(level - BITS, node)]))
+    }
+
+    /// Returns a new vector with the item at `idx` replaced. `idx == len` is the same as `push`.
+    /// Returns `None` if the index is out of bounds.
+    pub fn set(&self, idx: usize, item: T)->Option<Self> {
+        if idx == self.len {
+            return Some(self.push(item));
+        }
+        if idx > self.len {
+            return None;
+        }
+
+        let tail_offset = self.tail_offset();
+        if idx >= tail_offset {
+            let mut tail = (*self.tail).clone();
+            tail[idx - tail_offset] = item;
+
+            return Some(PVec {
+                len: self.len,
+                shift: self.shift,
+                root: self.root.clone(),
+                tail: Rc::new(tail),
+            });
+        }
+
+        return Some(PVec {
+            len: self.len,
+            shift: self.shift,
+            root: Self::set_in(self.shift, &self.root, idx, item),
+            tail: self.tail.clone(),
+        });
+    }
+
+    fn set_in(level: usize, node: &Rc<VecNode<T>>, idx: usize, item: T)->Rc<VecNode<T>> {
+        match &**node {
+            VecNode::Leaf(items)=>{
+                let mut items = items.clone();
+                items[idx & MASK] = item;
+
+                Rc::new(VecNode::Leaf(items))
+            },
+            VecNode::Branch(children)=>{
+                let mut children = children.clone();
+                let sub_idx = (idx >> level) & MASK;
+                children[sub_idx] = Self::set_in(level - BITS, &children[sub_idx], idx, item);
+
+                Rc::new(VecNode::Branch(children))
+            },
+        }
+    }
+}
+
+
+fn hash_key<K: Hash>(key: &K)->u64 {
+    let mut hasher = FxHasher::default();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+enum MapNode<K, V> {
+    Branch {
+        bitmap: u32,
+        children: Vec<Rc<MapNode<K, V>>>,
+    },
+    /// All of the entries have the same hash. There is usually only one.
+    Leaf {
+        hash: u64,
+        entries: Vec<(K, V)>,
+    },
+}
+impl<K: Clone + Eq, V: Clone> MapNode<K, V> {
+    /// Returns the new node and if the key was newly added
+    fn insert(node: &Rc<Self>, shift: usize, hash: u64, key: K, value: V)->(Rc<Self>, bool) {
+        match &**node {
+            Self::Leaf{hash: leaf_hash, entries}=>{
+                if *leaf_hash == hash {
+                    let mut entries = entries.clone();
+                    let mut added = true;
+                    if let Some(entry) = entries.iter_mut().find(|(k, _)|*k == key) {
+                        entry.1 = value;
+                        added = false;
+                    } else {
+                        entries.push((key, value));
+                    }
+
+                    return (Rc::new(Self::Leaf{hash, entries}), added);
+                }
+
+                let new_leaf = Rc::new(Self::Leaf {
+                    hash,
+                    entries: vec![(key, value)],
+                });
+
+                return (Self::merge(shift, node.clone(), *leaf_hash, new_leaf, hash), true);
+            },
+            Self::Branch{bitmap, children}=>{
+                let bit = 1 << ((hash >> shift) as usize & MASK);
+                let idx = (bitmap & (bit - 1)).count_ones() as usize;
+                let mut children = children.clone();
+
+                if bitmap & bit != 0 {
+                    let (child, added) = Self::insert(&children[idx], shift + BITS, hash, key, value);
+                    children[idx] = child;
+
+                    return (Rc::new(Self::Branch{bitmap: *bitmap, children}), added);
+                }
+
+                children.insert(idx, Rc::new(Self::Leaf {
+                    hash,
+                    entries: vec![(key, value)],
+                }));
+
+                return (Rc::new(Self::Branch{bitmap: bitmap | bit, children}), true);
+            },
+        }
+    }
+
+    /// Make a branch holding two leaves with different hashes
+    fn merge(shift: usize, node1: Rc<Self>, hash1: u64, node2: Rc<Self>, hash2: u64)->Rc<Self> {
+        let idx1 = (hash1 >> shift) as usize & MASK;
+        let idx2 = (hash2 >> shift) as usize & MASK;
+
+        if idx1 == idx2 {
+            return Rc::new(Self::Branch {
+                bitmap: 1 << idx1,
+                children: vec![Self::merge(shift + BITS, node1, hash1, node2, hash2)],
+            });
+        }
+
+        let children = if idx1 < idx2 {
+            vec![node1, node2]
+        } else {
+            vec![node2, node1]
+        };
+
+        Rc::new(Self::Branch {
+            bitmap: (1 << idx1) | (1 << idx2),
+            children,
+        })
+    }
+
+    /// `None` if the key was not found, `Some(None)` if the node is now empty, and `Some(Some(_))`
+    /// for the replacement node.
+    fn remove(node: &Rc<Self>, shift: usize, hash: u64, key: &K)->Option<Option<Rc<Self>>> {
+        match &**node {
+            Self::Leaf{hash: leaf_hash, entries}=>{
+                if *leaf_hash != hash {return None}
+
+                let idx = entries.iter().position(|(k, _)|k == key)?;
+                if entries.len() == 1 {
+                    return Some(None);
+                }
+
+                let mut entries = entries.clone();
+                entries.remove(idx);
+
+                return Some(Some(Rc::new(Self::Leaf{hash, entries})));
+            },
+            Self::Branch{bitmap, children}=>{
+                let bit = 1 << ((hash >> shift) as usize & MASK);
+                if bitmap & bit == 0 {return None}
+                let idx = (bitmap & (bit - 1)).count_ones() as usize;
+
+                let mut children = children.clone();
+                let mut bitmap = *bitmap;
+                match Self::remove(&children[idx], shift + BITS, hash, key)? {
+                    Some(child)=>children[idx] = child,
+                    None=>{
+                        children.remove(idx);
+                        bitmap &= !bit;
+                    },
+                }
+
+                if children.is_empty() {
+                    return Some(None);
+                }
+
+                // a lone leaf can live at any level, so pull it up
+                if children.len() == 1 && matches!(&*children[0], Self::Leaf{..}) {
+                    return Some(Some(children.pop().unwrap()));
+                }
+
+                return Some(Some(Rc::new(Self::Branch{bitmap, children})));
+            },
+        }
+    }
+
+    fn get<'a>(mut node: &'a Rc<Self>, hash: u64, key: &K)->Option<&'a V> {
+        let mut shift = 0;
+        loop {
+            match &**node {
+                Self::Leaf{hash: leaf_hash, entries}=>{
+                    if *leaf_hash != hash {return None}
+
+                    return entries.iter()
+                        .find(|(k, _)|k == key)
+                        .map(|(_, v)|v);
+                },
+                Self::Branch{bitmap, children}=>{
+                    let bit = 1 << ((hash >> shift) as usize & MASK);
+                    if bitmap & bit == 0 {return None}
+                    let idx = (bitmap & (bit - 1)).count_ones() as usize;
+
+                    node = &children[idx];
+                    shift += BITS;
+                },
+            }
+        }
+    }
+}
+impl<K, V> MapNode<K, V> {
+    fn for_each<F: FnMut(&K, &V)>(&self, f: &mut F) {
+        match self {
+            Self::Branch{children,..}=>children.iter().for_each(|c|c.for_each(f)),
+            Self::Leaf{entries,..}=>entries.iter().for_each(|(k, v)|f(k, v)),
+        }
+    }
+}
+
+pub struct PMap<K, V> {
+    len: usize,
+    root: Option<Rc<MapNode<K, V>>>,
+}
+impl<K, V> Clone for PMap<K, V> {
+    fn clone(&self)->Self {
+        PMap {
+            len: self.len,
+            root: self.root.clone(),
+        }
+    }
+}
+impl<K: Debug, V: Debug> Debug for PMap<K, V> {
+    fn fmt(&self, f: &mut Formatter)->FmtResult {
+        let mut map = f.debug_map();
+        self.for_each(|k, v|{map.entry(k, v);});
+        map.finish()
+    }
+}
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq> PartialEq for PMap<K, V> {
+    fn eq(&self, other: &Self)->bool {
+        if self.len != other.len {return false}
+
+        let mut equal = true;
+        self.for_each(|k, v|{
+            if equal {
+                equal = other.get(k) == Some(v);
+            }
+        });
+
+        return equal;
+    }
+}
+impl<K, V> PMap<K, V> {
+    pub fn new()->Self {
+        PMap {
+            len: 0,
+            root: None,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self)->usize {
+        self.len
+    }
+
+    /// Visit every entry. The order depends on the hashes of the keys.
+    pub fn for_each<F: FnMut(&K, &V)>(&self, mut f: F) {
+        if let Some(root) = &self.root {
+            root.for_each(&mut f);
+        }
+    }
+}
+impl<K: Clone + Eq + Hash, V: Clone> PMap<K, V> {
+    pub fn get(&self, key: &K)->Option<&V> {
+        let root = self.root.as_ref()?;
+
+        MapNode::get(root, hash_key(key), key)
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &K)->bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns a new map with the key set to the value
+    pub fn insert(&self, key: K, value: V)->Self {
+        let hash = hash_key(&key);
+
+        match &self.root {
+            Some(root)=>{
+                let (root, added) = MapNode::insert(root, 0, hash, key, value);
+
+                PMap {
+                    len: self.len + added as usize,
+                    root: Some(root),
+                }
+            },
+            None=>PMap {
+                len: 1,
+                root: Some(Rc::new(MapNode::Leaf {
+                    hash,
+                    entries: vec![(key, value)],
+                })),
+            },
+        }
+    }
+
+    /// Returns a new map without the key. If the key is not in the map, then this is just a cheap
+    /// clone.
+    pub fn remove(&self, key: &K)->Self {
+        let Some(root) = &self.root else {return self.clone()};
+
+        match MapNode::remove(root, 0, hash_key(key), key) {
+            Some(root)=>PMap {
+                len: self.len - 1,
+                root,
+            },
+            None=>self.clone(),
+        }
+    }
+}
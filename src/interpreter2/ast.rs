@@ -626,6 +626,11 @@ impl ConvertState {
         self.instructions.push(Instruction::Char(c));
     }
 
+    #[inline]
+    pub fn byte(&mut self, b: u8) {
+        self.instructions.push(Instruction::Byte(b));
+    }
+
     #[inline]
     pub fn field(&mut self, name: Ident) {
         self.instructions.push(Instruction::Field(name));
@@ -885,6 +890,7 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
         RefExpr::Float(f)=>state.float(f),
         RefExpr::String(s)=>state.string(s),
         RefExpr::Char(c)=>state.char(c),
+        RefExpr::Byte(b)=>state.byte(b),
         RefExpr::Ident(i)=>{
             let slot = state.lookup_var(i)
                 .ok_or(anyhow!("Var {} does not exist", i))?;
@@ -903,6 +909,7 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
             let (_, slot) = state.def_var(name)?;
             state.set_var(slot);
         },
+        RefExpr::DefConst{..}=>bail!("`defconst` is not supported in interpreter2"),
         RefExpr::Set{name, data}=>{
             convert_single_expr(state, todos, *data, is_tail)?;
 
@@ -922,6 +929,10 @@ fn convert_single_expr<'a, 'b>(state: &mut ConvertState, todos: &mut Todos<'a, '
             state.set_path(slot, path);
         },
         RefExpr::Object(_)=>panic!("Not supported in the new interpreter!"),
+        RefExpr::DefStruct{..}=>bail!("`defstruct` is not supported in interpreter2"),
+        RefExpr::FString{..}=>bail!("f-strings are not supported in interpreter2"),
+        RefExpr::Super{..}=>bail!("`super` is not supported in interpreter2"),
+        RefExpr::For{..}=>bail!("`for` is not supported in interpreter2"),
         RefExpr::Path(path)=>{
             let mut path_iter = path.into_iter();
             let var = path_iter.next().unwrap();
@@ -55,6 +55,12 @@ use std::{
     ptr,
     mem,
 };
+use crate::typed_array::{
+    TypedArray,
+    Element,
+    ElementOp,
+    Operand,
+};
 use super::{
     Interpreter,
     Ident,
@@ -75,8 +81,8 @@ use super::{
 /// Vulkan stuff, and interop with WASM or other languages.
 ///
 /// ## Uses
-/// The `BasicObject` type and `GcParams` type implement this, and so can be used seamlessly in the
-/// language as objects.
+/// The `BasicObject`, `TypedArray`, and `GcParams` types implement this, and so can be used
+/// seamlessly in the language as objects.
 ///
 /// ## Rant
 /// The sky is really the limit here. I could probably use this to add some dynamic loading logic
@@ -662,6 +668,118 @@ impl Object for BasicObject {
     }
 }
 
+fn element_primitive(element: Element)->Primitive {
+    match element {
+        Element::Float(f)=>Primitive::Float(f),
+        Element::Int(i)=>Primitive::Int(i),
+        Element::Byte(b)=>Primitive::Byte(b),
+    }
+}
+
+fn primitive_element(data: &Primitive)->Result<Element> {
+    match data {
+        Primitive::Float(f)=>Ok(Element::Float(*f)),
+        Primitive::Int(i)=>Ok(Element::Int(*i)),
+        Primitive::Byte(b)=>Ok(Element::Byte(*b)),
+        _=>bail!("Typed arrays can only hold ints, floats, and bytes"),
+    }
+}
+
+/// The native behind the `typedArray` global. Takes the kind as a string and a list of numbers, and
+/// makes a new typed array from them.
+pub fn make_typed_array(params: ObjectParams, args: Vec<Primitive>)->Result<Primitive> {
+    let [Primitive::String(kind), Primitive::Ref(items)] = args.as_slice() else {
+        bail!("`typedArray` takes a kind string and a list");
+    };
+    let Data::List(items) = &**items else {
+        bail!("`typedArray` takes a kind string and a list");
+    };
+
+    let mut arr = TypedArray::new(kind)?;
+    for item in items.iter() {
+        arr.push(primitive_element(item)?)?;
+    }
+
+    return Ok(Primitive::Ref(params.interpreter.gc.alloc(Data::Object(Box::new(arr)))));
+}
+
+/// Typed arrays don't hold any references, so there is nothing to trace. Fields are `length` and
+/// `kind`, and other fields error. No fields can be set.
+impl Object for TypedArray {
+    fn call_method<'a>(&mut self, name: Ident, params: ObjectParams<'a>, args: Vec<Primitive>, _: DataRef)->Result<Primitive> {
+        let name = params.state.interner.get(name);
+        let op = match name {
+            "sum"=>return Ok(element_primitive(self.sum())),
+            "min"=>return Ok(self.min().map(element_primitive).unwrap_or(Primitive::None)),
+            "max"=>return Ok(self.max().map(element_primitive).unwrap_or(Primitive::None)),
+            "get"=>{
+                let [idx] = args.as_slice() else {bail!("TypedArray.get takes an index")};
+                let idx = idx.clone().int_or(anyhow!("TypedArray.get only takes int indices"))?;
+                return match usize::try_from(idx).ok().and_then(|idx|self.get(idx)) {
+                    Some(element)=>Ok(element_primitive(element)),
+                    None=>bail!("Index out of bounds"),
+                };
+            },
+            "set"=>{
+                let [idx, data] = args.as_slice() else {bail!("TypedArray.set takes an index and a value")};
+                let idx = idx.clone().int_or(anyhow!("TypedArray.set only takes int indices"))?;
+                let Ok(idx) = usize::try_from(idx) else {bail!("Index out of bounds")};
+                self.set(idx, primitive_element(data)?)?;
+                return Ok(Primitive::None);
+            },
+            "push"=>{
+                let [data] = args.as_slice() else {bail!("TypedArray.push takes a value")};
+                self.push(primitive_element(data)?)?;
+                return Ok(Primitive::None);
+            },
+            "dot"=>{
+                let [Primitive::Ref(other)] = args.as_slice() else {bail!("TypedArray.dot takes a typed array")};
+                let Some(other) = other.downcast_object::<Self>() else {
+                    bail!("TypedArray.dot takes a typed array");
+                };
+                return Ok(element_primitive(self.dot(other)?));
+            },
+            "add"=>ElementOp::Add,
+            "sub"=>ElementOp::Sub,
+            "mul"=>ElementOp::Mul,
+            "div"=>ElementOp::Div,
+            name=>bail!("TypedArray does not have the method `{}`", name),
+        };
+
+        let out = match args.as_slice() {
+            [Primitive::Ref(other)]=>match other.downcast_object::<Self>() {
+                Some(other)=>self.elementwise(Operand::Array(other), op)?,
+                None=>bail!("TypedArray elementwise methods take a typed array or a number"),
+            },
+            [data]=>self.elementwise(Operand::Element(primitive_element(data)?), op)?,
+            _=>bail!("TypedArray elementwise methods take a typed array or a number"),
+        };
+
+        return Ok(Primitive::Ref(params.interpreter.gc.alloc(Data::Object(Box::new(out)))));
+    }
+
+    fn compare(&self, other: &Box<dyn Object>)->bool {
+        let Some(other_ref) = <dyn Any>::downcast_ref::<Self>(other) else {return false};
+        self == other_ref
+    }
+
+    fn trace(&self, _: &mut dyn GcTracer) {
+        // Nothing to do here
+    }
+
+    fn get_field<'a>(&self, name: Ident, params: ObjectParams<'a>)->Result<Primitive> {
+        match params.state.interner.get(name) {
+            "length"=>Ok(Primitive::Int(self.len() as i64)),
+            "kind"=>Ok(Primitive::String(Rc::new(self.kind().to_string()))),
+            name=>bail!("TypedArray does not have the field {}", name),
+        }
+    }
+
+    fn set_field<'a>(&mut self, name: Ident, params: ObjectParams<'a>, _: Primitive)->Result<()> {
+        bail!("Cannot set the field {} on a TypedArray", params.state.interner.get(name));
+    }
+}
+
 /// The `initialUnits` field is not exposed in the `Object` interface because it is only used
 /// at initialization.
 #[derive(Debug, Copy, Clone, PartialEq)]
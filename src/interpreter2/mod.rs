@@ -52,6 +52,7 @@ pub const DEFAULT_GLOBALS: &'static [&'static str] = &[
 
     "core",
     "std",
+    "typedArray",
 ];
 
 
@@ -114,6 +115,12 @@ impl Interpreter {
         core_dr.set_permanent();
         globals.push(Primitive::Ref(core_dr).rooted());
 
+        assert!(globals_iter.next() == Some(&"std"));
+        globals.push(Primitive::None);
+
+        assert!(globals_iter.next() == Some(&"typedArray"));
+        globals.push(Primitive::NativeFunc(make_typed_array, ArgCount::Exact(2)));
+
         Interpreter {
             globals,
             vars: Vec::new(),
@@ -28,6 +28,10 @@ pub enum Token<'a> {
     #[token("\"", string)]
     String(String),
 
+    /// `f"..."`. This is the raw text between the quotes; the parser handles the escapes and `{}`s.
+    #[token("f\"", fstring)]
+    FString(&'a str),
+
     #[regex("#x[0-9A-Fa-f]{2}", parse_byte_hex)]
     #[regex("#b[01]{1,8}", parse_byte_bin)]
     Byte(u8),
@@ -71,6 +75,20 @@ impl<'a> TokenTrait for Token<'a> {
     fn eof()->Self {Self::EOF}
 }
 
+/// The same rules as the `Ident` regex, for names that don't go through the lexer, like the
+/// placeholders in f-strings
+pub fn is_ident(s: &str)->bool {
+    let mut chars = s.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    if first.is_ascii_digit() || "/:;\\ .\t\r\n()[]{}\"'#".contains(first) {
+        return false;
+    }
+
+    return chars.all(|c|!"/ .\t\r\n()[]{}\"".contains(c));
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum StartOrEnd {
     Start,
@@ -135,6 +153,29 @@ fn string<'a>(l: &mut Lexer<'a, Token<'a>>)->Option<String> {
     return Some(s);
 }
 
+fn fstring<'a>(l: &mut Lexer<'a, Token<'a>>)->Option<&'a str> {
+    let rem = l.remainder();
+    let mut escape = false;
+
+    for (idx, c) in rem.char_indices() {
+        if escape {
+            escape = false;
+            continue;
+        }
+
+        match c {
+            '"'=>{
+                l.bump(idx + 1);
+                return Some(&rem[..idx]);
+            },
+            '\\'=>escape = true,
+            _=>{},
+        }
+    }
+
+    return None;
+}
+
 fn strip_first<'a>(l: &mut Lexer<'a, Token<'a>>)->&'a str {
     &l.slice()[1..]
 }
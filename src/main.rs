@@ -27,6 +27,7 @@ mod parser;
 mod ast;
 mod interpreter;
 mod interpreter2;
+mod typed_array;
 mod repl;
 
 
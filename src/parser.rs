@@ -195,7 +195,9 @@ impl<'a> MyParser<'a> {
             Token::Number(n)=>Ok(Expr::Number(n)),
             Token::Float(f)=>Ok(Expr::Float(f)),
             Token::String(s)=>Ok(Expr::String(s)),
+            Token::FString(s)=>self.parse_fstring(s),
             Token::Char(c)=>Ok(Expr::Char(c)),
+            Token::Byte(b)=>Ok(Expr::Byte(b)),
             Token::Ident(i)=>if i == "None" {
                 Ok(Expr::None)
             } else {
@@ -226,6 +228,78 @@ impl<'a> MyParser<'a> {
         }
     }
 
+    /// `f"{name} costs {price:.2}"` becomes `(std/string/fmt "{} costs {:.2}" name price)`. The
+    /// placeholders can only hold an identifier or a path.
+    fn parse_fstring(&self, raw: &'a str)->Result<Expr<'a>> {
+        let mut template = String::new();
+        let mut args = Vec::new();
+        let mut chars = raw.char_indices().peekable();
+
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\'=>{
+                    let c = match chars.next() {
+                        Some((_, '"'))=>'"',
+                        Some((_, '\\'))=>'\\',
+                        Some((_, 't'))=>'\t',
+                        Some((_, 'r'))=>'\r',
+                        Some((_, 'n'))=>'\n',
+                        Some((_, '0'))=>'\0',
+                        _=>bail!(self.error("Invalid escape in f-string")),
+                    };
+                    template.push(c);
+                },
+                // escaped braces are left for `fmt` to handle
+                '{' if chars.peek().map(|(_, c)|*c) == Some('{')=>{
+                    chars.next();
+                    template.push_str("{{");
+                },
+                '}' if chars.peek().map(|(_, c)|*c) == Some('}')=>{
+                    chars.next();
+                    template.push_str("}}");
+                },
+                '{'=>{
+                    let end = loop {
+                        match chars.next() {
+                            Some((end, '}'))=>break end,
+                            Some(_)=>{},
+                            None=>bail!(self.error("Unclosed `{` in f-string")),
+                        }
+                    };
+                    let inner = &raw[idx + 1..end];
+                    let (name, spec) = match inner.find(':') {
+                        Some(colon)=>(&inner[..colon], &inner[colon..]),
+                        None=>(inner, ""),
+                    };
+
+                    template.push('{');
+                    template.push_str(spec);
+                    template.push('}');
+
+                    let name = name.trim();
+                    if name.is_empty() {
+                        bail!(self.error("f-string placeholders must have a name"));
+                    }
+                    if !name.split('/').all(is_ident) {
+                        bail!(self.error(format!("`{name}` is not a valid name for an f-string placeholder")));
+                    }
+                    if name.contains('/') {
+                        args.push(Expr::Path(name.split('/').collect()));
+                    } else {
+                        args.push(Expr::Ident(name));
+                    }
+                },
+                '}'=>bail!(self.error("Unmatched `}` in f-string. Use `}}` for a literal `}`")),
+                c=>template.push(c),
+            }
+        }
+
+        return Ok(Expr::FString {
+            template,
+            args,
+        });
+    }
+
     fn match_hash_literal(&self, lit: &str)->Result<Expr<'static>> {
         match lit {
             "t"=>Ok(Expr::True),
@@ -243,6 +317,8 @@ impl<'a> MyParser<'a> {
                 "fn"=>return self.parse_fn(),
                 "cond"=>return self.parse_cond(),
                 "def"=>return self.parse_def(),
+                "defconst"=>return self.parse_defconst(),
+                "defstruct"=>return self.parse_defstruct(),
                 "set"=>return self.parse_set(),
                 "defn"=>return self.parse_defn(),
                 "quote"=>return self.parse_quote(),
@@ -250,6 +326,8 @@ impl<'a> MyParser<'a> {
                 "object"=>return self.parse_object(),
                 "module"=>return self.parse_module(),
                 "chain"=>return self.parse_chain(),
+                "super"=>return self.parse_super(),
+                "for"=>return self.parse_for(),
                 _=>{},
             },
             _=>{},
@@ -293,6 +371,47 @@ impl<'a> MyParser<'a> {
         return Ok(Expr::Begin(items));
     }
 
+    fn parse_super(&mut self)->Result<Expr<'a>> {
+        self.match_ident("super")?;
+
+        let vtable = self.parse_expr()
+            .map(Box::new)
+            .context("Super vtable")?;
+
+        let method = self.dot_ident()
+            .context("Super method")?;
+
+        let args = self.parse_end_listed_items(Self::parse_expr)
+            .context("Super args")?;
+
+        return Ok(Expr::Super {
+            vtable,
+            method,
+            args,
+        });
+    }
+
+    fn parse_for(&mut self)->Result<Expr<'a>> {
+        self.match_ident("for")?;
+
+        self.start_vector()?;
+        let var = self.ident()
+            .context("For variable")?;
+        let iter = self.parse_expr()
+            .map(Box::new)
+            .context("For iterator")?;
+        self.end_vector()?;
+
+        let body = self.parse_end_listed_items(Self::parse_expr)
+            .context("For body")?;
+
+        return Ok(Expr::For {
+            var,
+            iter,
+            body,
+        });
+    }
+
     fn parse_module(&mut self)->Result<Expr<'a>> {
         self.match_ident("module")?;
         let name = self.ident()?;
@@ -321,8 +440,16 @@ impl<'a> MyParser<'a> {
                 return Ok(Field::Full(name, e));
             },
             Token::DotIdent(_)=>Ok(Field::Shorthand(self.dot_ident()?)),
+            Token::Splat=>{
+                self.next();
+
+                let e = self.parse_expr()
+                    .context("Object splat")?;
+
+                return Ok(Field::Splat(e));
+            },
             Token::EOF=>bail!(ReplContinue(self.error("Unexpected token. Expected `(` or `[`"))),
-            _=>bail!("Unexpected token. Expected `(` or DotIdent"),
+            _=>bail!("Unexpected token. Expected `(`, DotIdent, or `...`"),
         }
     }
 
@@ -467,6 +594,56 @@ impl<'a> MyParser<'a> {
         });
     }
 
+    fn parse_defconst(&mut self)->Result<Expr<'a>> {
+        self.match_ident("defconst")?;
+
+        let name = self.ident()
+            .context("Defconst name")?;
+
+        let mut data = self.parse_expr()
+            .map(Box::new)
+            .context("Defconst data")?;
+
+        match &mut *data {
+            Expr::Fn(f)=>f.name = Some(name),
+            _=>{},
+        }
+
+        self.end_list()
+            .context("End defconst")?;
+
+        return Ok(Expr::DefConst {
+            name,
+            data,
+        });
+    }
+
+    fn parse_defstruct(&mut self)->Result<Expr<'a>> {
+        self.match_ident("defstruct")?;
+
+        let name = self.ident()
+            .context("Defstruct name")?;
+
+        let fields = self.parse_vector()
+            .context("Defstruct fields")?;
+        if fields.remainder.is_some() {
+            bail!(self.error("Struct fields cannot have a remainder"));
+        }
+        for (idx, field) in fields.items.iter().enumerate() {
+            if fields.items[..idx].contains(field) {
+                bail!(self.error(format!("Struct field `{field}` is defined more than once")));
+            }
+        }
+
+        self.end_list()
+            .context("End defstruct")?;
+
+        return Ok(Expr::DefStruct {
+            name,
+            fields: fields.items,
+        });
+    }
+
     fn parse_set(&mut self)->Result<Expr<'a>> {
         self.match_ident("set")?;
 
@@ -576,7 +753,9 @@ impl<'a> MyParser<'a> {
             Token::Number(n)=>Ok(Expr::Number(n)),
             Token::Float(f)=>Ok(Expr::Float(f)),
             Token::String(s)=>Ok(Expr::String(s)),
+            Token::FString(s)=>self.parse_fstring(s),
             Token::Char(c)=>Ok(Expr::Char(c)),
+            Token::Byte(b)=>Ok(Expr::Byte(b)),
             Token::DotIdent(s)=>Ok(Expr::DotIdent(s)),
             Token::Ident(i)=>if i == "None" {
                 Ok(Expr::None)
@@ -0,0 +1,297 @@
+//! Flat arrays of a single numeric type. They are shared by both interpreters, so numeric code
+//! doesn't have to box every element in its own `DataBox`.
+
+
+use anyhow::{
+    Result,
+    bail,
+};
+use std::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+
+
+/// One element of a `TypedArray`. This is what goes in and out of the arrays, and each
+/// interpreter converts it to and from its own data type.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Element {
+    Float(f64),
+    Int(i64),
+    Byte(u8),
+}
+impl Element {
+    /// Anything can become a float
+    fn as_f64(self)->f64 {
+        match self {
+            Self::Float(f)=>f,
+            Self::Int(i)=>i as f64,
+            Self::Byte(b)=>b as f64,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ElementOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// Either side of an elementwise op can be a whole array or a single element
+pub enum Operand<'a> {
+    Array(&'a TypedArray),
+    Element(Element),
+}
+
+/// Integer arrays wrap on overflow like the fixed-width types they hold. Use a list of Numbers if
+/// you need them to grow.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedArray {
+    F64(Vec<f64>),
+    I64(Vec<i64>),
+    U8(Vec<u8>),
+}
+impl TypedArray {
+    /// Makes an empty array of the named kind: `f64`, `i64`, or `u8`
+    pub fn new(kind: &str)->Result<Self> {
+        match kind {
+            "f64"=>Ok(Self::F64(Vec::new())),
+            "i64"=>Ok(Self::I64(Vec::new())),
+            "u8"=>Ok(Self::U8(Vec::new())),
+            _=>bail!("Unknown array kind `{kind}`. Expected `f64`, `i64`, or `u8`"),
+        }
+    }
+
+    pub fn kind(&self)->&'static str {
+        match self {
+            Self::F64(_)=>"f64",
+            Self::I64(_)=>"i64",
+            Self::U8(_)=>"u8",
+        }
+    }
+
+    pub fn len(&self)->usize {
+        match self {
+            Self::F64(items)=>items.len(),
+            Self::I64(items)=>items.len(),
+            Self::U8(items)=>items.len(),
+        }
+    }
+
+    /// Only used for the allocation size estimate
+    pub fn byte_capacity(&self)->usize {
+        match self {
+            Self::F64(items)=>items.capacity() * 8,
+            Self::I64(items)=>items.capacity() * 8,
+            Self::U8(items)=>items.capacity(),
+        }
+    }
+
+    pub fn get(&self, idx: usize)->Option<Element> {
+        match self {
+            Self::F64(items)=>items.get(idx).copied().map(Element::Float),
+            Self::I64(items)=>items.get(idx).copied().map(Element::Int),
+            Self::U8(items)=>items.get(idx).copied().map(Element::Byte),
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, element: Element)->Result<()> {
+        let len = self.len();
+        if idx >= len {
+            bail!("Index out of bounds");
+        }
+
+        match self {
+            Self::F64(items)=>items[idx] = element.as_f64(),
+            Self::I64(items)=>items[idx] = to_i64(element)?,
+            Self::U8(items)=>items[idx] = to_u8(element)?,
+        }
+
+        return Ok(());
+    }
+
+    /// The element is converted to the kind of the array. Errors if it can't be converted without
+    /// losing information, except ints becoming floats.
+    pub fn push(&mut self, element: Element)->Result<()> {
+        match self {
+            Self::F64(items)=>items.push(element.as_f64()),
+            Self::I64(items)=>items.push(to_i64(element)?),
+            Self::U8(items)=>items.push(to_u8(element)?),
+        }
+
+        return Ok(());
+    }
+
+    pub fn iter(&self)->impl Iterator<Item = Element> + '_ {
+        (0..self.len()).map(|idx|self.get(idx).unwrap())
+    }
+
+    /// The end is not included
+    pub fn slice(&self, start: usize, end: usize)->Result<Self> {
+        if start > end || end > self.len() {
+            bail!("Index out of bounds");
+        }
+
+        match self {
+            Self::F64(items)=>Ok(Self::F64(items[start..end].to_vec())),
+            Self::I64(items)=>Ok(Self::I64(items[start..end].to_vec())),
+            Self::U8(items)=>Ok(Self::U8(items[start..end].to_vec())),
+        }
+    }
+
+    /// Makes a new array of the same kind. Arrays have to be the same kind and length, and single
+    /// elements are converted to the kind of the array.
+    pub fn elementwise(&self, other: Operand, op: ElementOp)->Result<Self> {
+        if let Operand::Array(other) = &other {
+            if other.kind() != self.kind() {
+                bail!("Cannot mix `{}` and `{}` arrays", self.kind(), other.kind());
+            }
+            if other.len() != self.len() {
+                bail!("Arrays have different lengths: {} and {}", self.len(), other.len());
+            }
+        }
+        let rhs = |idx: usize|match &other {
+            Operand::Array(other)=>other.get(idx).unwrap(),
+            Operand::Element(e)=>*e,
+        };
+
+        match self {
+            Self::F64(items)=>{
+                let out = items.iter()
+                    .enumerate()
+                    .map(|(idx, l)|float_op(*l, rhs(idx).as_f64(), op))
+                    .collect();
+                return Ok(Self::F64(out));
+            },
+            Self::I64(items)=>{
+                let mut out = Vec::with_capacity(items.len());
+                for (idx, l) in items.iter().enumerate() {
+                    out.push(int_op(*l, to_i64(rhs(idx))?, op)?);
+                }
+                return Ok(Self::I64(out));
+            },
+            Self::U8(items)=>{
+                let mut out = Vec::with_capacity(items.len());
+                for (idx, l) in items.iter().enumerate() {
+                    out.push(byte_op(*l, to_u8(rhs(idx))?, op)?);
+                }
+                return Ok(Self::U8(out));
+            },
+        }
+    }
+
+    /// Integer sums are `Int`s so byte arrays don't overflow at 255
+    pub fn sum(&self)->Element {
+        match self {
+            Self::F64(items)=>Element::Float(items.iter().sum()),
+            Self::I64(items)=>Element::Int(items.iter().fold(0i64, |acc, i|acc.wrapping_add(*i))),
+            Self::U8(items)=>Element::Int(items.iter().fold(0i64, |acc, b|acc.wrapping_add(*b as i64))),
+        }
+    }
+
+    pub fn dot(&self, other: &Self)->Result<Element> {
+        if other.kind() != self.kind() {
+            bail!("Cannot mix `{}` and `{}` arrays", self.kind(), other.kind());
+        }
+        if other.len() != self.len() {
+            bail!("Arrays have different lengths: {} and {}", self.len(), other.len());
+        }
+
+        match (self, other) {
+            (Self::F64(l), Self::F64(r))=>Ok(Element::Float(l.iter().zip(r).map(|(l, r)|l * r).sum())),
+            (Self::I64(l), Self::I64(r))=>Ok(Element::Int(l.iter()
+                .zip(r)
+                .fold(0i64, |acc, (l, r)|acc.wrapping_add(l.wrapping_mul(*r)))
+            )),
+            (Self::U8(l), Self::U8(r))=>Ok(Element::Int(l.iter()
+                .zip(r)
+                .fold(0i64, |acc, (l, r)|acc.wrapping_add(*l as i64 * *r as i64))
+            )),
+            _=>unreachable!(),
+        }
+    }
+
+    /// `None` if the array is empty. NaNs are ignored unless everything is NaN.
+    pub fn min(&self)->Option<Element> {
+        match self {
+            Self::F64(items)=>items.iter().copied().reduce(f64::min).map(Element::Float),
+            Self::I64(items)=>items.iter().copied().min().map(Element::Int),
+            Self::U8(items)=>items.iter().copied().min().map(Element::Byte),
+        }
+    }
+
+    /// Same as `min`
+    pub fn max(&self)->Option<Element> {
+        match self {
+            Self::F64(items)=>items.iter().copied().reduce(f64::max).map(Element::Float),
+            Self::I64(items)=>items.iter().copied().max().map(Element::Int),
+            Self::U8(items)=>items.iter().copied().max().map(Element::Byte),
+        }
+    }
+}
+impl Display for TypedArray {
+    fn fmt(&self, f: &mut Formatter)->FmtResult {
+        write!(f, "#{}[", self.kind())?;
+        for (idx, element) in self.iter().enumerate() {
+            if idx > 0 {write!(f, " ")?}
+            match element {
+                Element::Float(x)=>write!(f, "{x}")?,
+                Element::Int(i)=>write!(f, "{i}")?,
+                Element::Byte(b)=>write!(f, "{b}")?,
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+
+fn to_i64(element: Element)->Result<i64> {
+    match element {
+        Element::Int(i)=>Ok(i),
+        Element::Byte(b)=>Ok(b as i64),
+        Element::Float(_)=>bail!("Type error: `i64` arrays cannot hold Floats"),
+    }
+}
+
+fn to_u8(element: Element)->Result<u8> {
+    match element {
+        Element::Byte(b)=>Ok(b),
+        Element::Int(i)=>match u8::try_from(i) {
+            Ok(b)=>Ok(b),
+            Err(_)=>bail!("`u8` arrays can only hold numbers between 0 and 255, not {i}"),
+        },
+        Element::Float(_)=>bail!("Type error: `u8` arrays cannot hold Floats"),
+    }
+}
+
+fn float_op(l: f64, r: f64, op: ElementOp)->f64 {
+    match op {
+        ElementOp::Add=>l + r,
+        ElementOp::Sub=>l - r,
+        ElementOp::Mul=>l * r,
+        ElementOp::Div=>l / r,
+    }
+}
+
+fn int_op(l: i64, r: i64, op: ElementOp)->Result<i64> {
+    match op {
+        ElementOp::Add=>Ok(l.wrapping_add(r)),
+        ElementOp::Sub=>Ok(l.wrapping_sub(r)),
+        ElementOp::Mul=>Ok(l.wrapping_mul(r)),
+        ElementOp::Div if r == 0=>bail!("Division by zero"),
+        ElementOp::Div=>Ok(l.wrapping_div(r)),
+    }
+}
+
+fn byte_op(l: u8, r: u8, op: ElementOp)->Result<u8> {
+    match op {
+        ElementOp::Add=>Ok(l.wrapping_add(r)),
+        ElementOp::Sub=>Ok(l.wrapping_sub(r)),
+        ElementOp::Mul=>Ok(l.wrapping_mul(r)),
+        ElementOp::Div if r == 0=>bail!("Division by zero"),
+        ElementOp::Div=>Ok(l / r),
+    }
+}